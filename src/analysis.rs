@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::SearchResult;
+use crate::secret::Redacted;
+
+/// The number of entries in the top lists of a [PasswordReport]
+const TOP_N: usize = 10;
+
+/// A value and the number of its occurrences
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ValueCount {
+    /// The value
+    pub value: String,
+    /// The number of occurrences
+    pub count: usize,
+}
+
+/// The character classes of a password
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CharacterClasses {
+    /// The number of passwords containing lower case letters
+    pub lowercase: usize,
+    /// The number of passwords containing upper case letters
+    pub uppercase: usize,
+    /// The number of passwords containing digits
+    pub digits: usize,
+    /// The number of passwords containing any other character
+    pub symbols: usize,
+    /// The number of passwords consisting of lower case letters only
+    pub only_lowercase: usize,
+    /// The number of passwords consisting of digits only
+    pub only_digits: usize,
+    /// The number of passwords containing all four classes
+    pub all_classes: usize,
+}
+
+/// A password that is used by multiple accounts
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PasswordReuse {
+    /// The password
+    pub password: String,
+    /// The accounts using the password, the email or username of the entries
+    pub accounts: Vec<String>,
+}
+
+/// Prints the accounts, replacing the password with [REDACTED](crate::REDACTED)
+impl Debug for PasswordReuse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordReuse")
+            .field("password", &Redacted)
+            .field("accounts", &self.accounts)
+            .finish()
+    }
+}
+
+/// The kind of password exposure of the accounts in a result
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Exposure {
+    /// The number of accounts with at least one plaintext password
+    pub plaintext: usize,
+    /// The number of accounts with hashed passwords only
+    pub hash_only: usize,
+    /// The number of accounts without any password
+    pub none: usize,
+}
+
+/// Statistics about the passwords exposed in a [SearchResult]
+///
+/// The [Debug] implementation redacts the passwords and base words.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PasswordReport {
+    /// The number of entries that were analysed
+    pub entries: usize,
+    /// The number of plaintext passwords
+    pub passwords: usize,
+    /// The number of distinct plaintext passwords
+    pub unique_passwords: usize,
+    /// The number of passwords per length
+    pub length_distribution: BTreeMap<usize, usize>,
+    /// The composition of the passwords
+    pub character_classes: CharacterClasses,
+    /// The most common passwords
+    pub top_passwords: Vec<ValueCount>,
+    /// The most common base words, the lower case passwords without leading and trailing
+    /// digits and symbols
+    pub top_base_words: Vec<ValueCount>,
+    /// Passwords that are used by more than one account, ordered by the number of accounts
+    pub reuse: Vec<PasswordReuse>,
+    /// The kind of exposure of the accounts
+    pub exposure: Exposure,
+    /// The databases most entries originate from
+    pub top_databases: Vec<ValueCount>,
+}
+
+/// Prints all fields, replacing the passwords and base words with [REDACTED](crate::REDACTED)
+impl Debug for PasswordReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let redacted = |counts: &[ValueCount]| -> Vec<_> {
+            counts.iter().map(|x| RedactedCount(x.count)).collect()
+        };
+        f.debug_struct("PasswordReport")
+            .field("entries", &self.entries)
+            .field("passwords", &self.passwords)
+            .field("unique_passwords", &self.unique_passwords)
+            .field("length_distribution", &self.length_distribution)
+            .field("character_classes", &self.character_classes)
+            .field("top_passwords", &redacted(&self.top_passwords))
+            .field("top_base_words", &redacted(&self.top_base_words))
+            .field("reuse", &self.reuse)
+            .field("exposure", &self.exposure)
+            .field("top_databases", &self.top_databases)
+            .finish()
+    }
+}
+
+/// Debug helper that prints a [ValueCount] with its value replaced by [REDACTED](crate::REDACTED)
+struct RedactedCount(usize);
+
+impl Debug for RedactedCount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueCount")
+            .field("value", &Redacted)
+            .field("count", &self.0)
+            .finish()
+    }
+}
+
+impl PasswordReport {
+    /// Analyse the passwords of a result.
+    ///
+    /// Accounts are identified by their email, or their username if the email is missing.
+    /// Entries without both are counted, but not attributed to an account.
+    pub fn from_result(result: &SearchResult) -> Self {
+        let mut report = PasswordReport {
+            entries: result.entries.len(),
+            ..Default::default()
+        };
+
+        let mut passwords: HashMap<&str, usize> = HashMap::new();
+        let mut base_words: HashMap<String, usize> = HashMap::new();
+        let mut databases: HashMap<&str, usize> = HashMap::new();
+        let mut accounts_by_password: HashMap<&str, HashSet<String>> = HashMap::new();
+        // Whether an account has a plaintext password and whether it has a hash
+        let mut accounts: HashMap<String, (bool, bool)> = HashMap::new();
+
+        for entry in &result.entries {
+            let account = entry
+                .email
+                .as_deref()
+                .or(entry.username.as_deref())
+                .map(str::to_lowercase);
+
+            if let Some(database) = &entry.database_name {
+                *databases.entry(database).or_default() += 1;
+            }
+
+            if let Some(account) = &account {
+                let exposure = accounts.entry(account.clone()).or_default();
+                exposure.0 |= entry.password.is_some();
+                exposure.1 |= entry.hashed_password.is_some();
+            }
+
+            let Some(password) = entry.password.as_deref() else {
+                continue;
+            };
+            report.passwords += 1;
+            *passwords.entry(password).or_default() += 1;
+            *report
+                .length_distribution
+                .entry(password.chars().count())
+                .or_default() += 1;
+            report.character_classes.add(password);
+
+            let base = base_word(password);
+            if !base.is_empty() {
+                *base_words.entry(base).or_default() += 1;
+            }
+
+            if let Some(account) = account {
+                accounts_by_password
+                    .entry(password)
+                    .or_default()
+                    .insert(account);
+            }
+        }
+
+        report.unique_passwords = passwords.len();
+        report.top_passwords = top(passwords.into_iter().map(|(k, v)| (k.to_string(), v)));
+        report.top_base_words = top(base_words);
+        report.top_databases = top(databases.into_iter().map(|(k, v)| (k.to_string(), v)));
+
+        report.reuse = accounts_by_password
+            .into_iter()
+            .filter(|(_, accounts)| accounts.len() > 1)
+            .map(|(password, accounts)| {
+                let mut accounts: Vec<_> = accounts.into_iter().collect();
+                accounts.sort();
+                PasswordReuse {
+                    password: password.to_string(),
+                    accounts,
+                }
+            })
+            .collect();
+        report.reuse.sort_by(|a, b| {
+            b.accounts
+                .len()
+                .cmp(&a.accounts.len())
+                .then_with(|| a.password.cmp(&b.password))
+        });
+
+        for (plaintext, hash) in accounts.into_values() {
+            match (plaintext, hash) {
+                (true, _) => report.exposure.plaintext += 1,
+                (false, true) => report.exposure.hash_only += 1,
+                (false, false) => report.exposure.none += 1,
+            }
+        }
+
+        report
+    }
+}
+
+impl CharacterClasses {
+    fn add(&mut self, password: &str) {
+        let lower = password.chars().any(|c| c.is_lowercase());
+        let upper = password.chars().any(|c| c.is_uppercase());
+        let digits = password.chars().any(|c| c.is_ascii_digit());
+        let symbols = password
+            .chars()
+            .any(|c| !c.is_lowercase() && !c.is_uppercase() && !c.is_ascii_digit());
+
+        self.lowercase += lower as usize;
+        self.uppercase += upper as usize;
+        self.digits += digits as usize;
+        self.symbols += symbols as usize;
+        self.only_lowercase += (lower && !upper && !digits && !symbols) as usize;
+        self.only_digits += (digits && !lower && !upper && !symbols) as usize;
+        self.all_classes += (lower && upper && digits && symbols) as usize;
+    }
+}
+
+/// Strip leading and trailing digits and symbols and convert to lower case,
+/// e.g. `Summer2023!` becomes `summer`
+fn base_word(password: &str) -> String {
+    password
+        .trim_matches(|c: char| !c.is_alphabetic())
+        .to_lowercase()
+}
+
+/// Retrieve the [TOP_N] values with the highest count, ties are ordered by value
+fn top(counts: impl IntoIterator<Item = (String, usize)>) -> Vec<ValueCount> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount { value, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts.truncate(TOP_N);
+    counts
+}
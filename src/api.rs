@@ -1,21 +1,42 @@
-use std::fmt::Write;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Display, Formatter, Write};
 use std::net::IpAddr;
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use log::{debug, error};
+use log::{debug, error, warn};
 use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, Proxy, StatusCode};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "tokio")]
 use tokio::time::sleep;
+use zeroize::Zeroize;
 
+use crate::budget::{Budget, BudgetState, Reservation};
+use crate::cassette::{CassetteMode, CassetteState, RecordedRequest};
 use crate::error::DehashedError;
+use crate::multi::{MultiSearchEntry, MultiSearchResult};
 use crate::res::{Entry, Response};
+use crate::secret::{Redacted, RedactedValues, SecretString, REDACTED};
 #[cfg(feature = "tokio")]
 use crate::Scheduler;
 
-const URL: &str = "https://api.dehashed.com/search";
+/// The default url of the search endpoint
+pub const DEFAULT_URL: &str = "https://api.dehashed.com/search";
+/// The default delay between two requests
+pub const DEFAULT_REQUEST_DELAY: Duration = Duration::from_millis(200);
+/// The maximum number of entries per page
+const PAGE_SIZE: usize = 10_000;
+/// The maximum number of pages dehashed allows to fetch for a single query
+const MAX_PAGES: usize = 3;
+/// The maximum number of entries that can be retrieved for a single query
+pub const RESULT_WINDOW: usize = PAGE_SIZE * MAX_PAGES;
+/// The characters used as prefixes when splitting a query
+const SPLIT_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+/// The query used for requests that are only interested in the account, not the entries
+const PROBE_QUERY: &str = "domain:example.com";
 const RESERVED: [char; 21] = [
     '+', '-', '=', '&', '|', '>', '<', '!', '(', ')', '{', '}', '[', ']', '^', '"', '~', '*', '?',
     ':', '\\',
@@ -50,22 +71,28 @@ pub enum SearchType {
     And(Vec<SearchType>),
 }
 
-impl ToString for SearchType {
-    fn to_string(&self) -> String {
+impl Display for SearchType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SearchType::Simple(x) => escape(x),
-            SearchType::Exact(x) => format!("\"{}\"", escape(x)),
-            SearchType::Regex(x) => format!("/{}/", escape(x)),
-            SearchType::Or(x) => x
-                .iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>()
-                .join(" OR "),
-            SearchType::And(x) => x
-                .iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<_>>()
-                .join(" "),
+            SearchType::Simple(x) => write!(f, "{}", escape(x)),
+            SearchType::Exact(x) => write!(f, "\"{}\"", escape(x)),
+            SearchType::Regex(x) => write!(f, "/{}/", escape(x)),
+            SearchType::Or(x) => write!(
+                f,
+                "{}",
+                x.iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            ),
+            SearchType::And(x) => write!(
+                f,
+                "{}",
+                x.iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
         }
     }
 }
@@ -97,23 +124,58 @@ pub enum Query {
     Address(SearchType),
 }
 
-impl ToString for Query {
-    fn to_string(&self) -> String {
-        match self {
-            Query::Email(x) => format!("email:{}", x.to_string()),
-            Query::IpAddress(x) => format!("ip_address:{}", x.to_string()),
-            Query::Username(x) => format!("username:{}", x.to_string()),
-            Query::Password(x) => format!("password:{}", x.to_string()),
-            Query::HashedPassword(x) => format!("hashed_password:{}", x.to_string()),
-            Query::Name(x) => format!("name:{}", x.to_string()),
-            Query::Domain(x) => format!("domain:{}", x.to_string()),
-            Query::Vin(x) => format!("vin:{}", x.to_string()),
-            Query::Phone(x) => format!("phone:{}", x.to_string()),
-            Query::Address(x) => format!("address:{}", x.to_string()),
+/// Combined [SearchType]s are grouped with parentheses, e.g. `email:("a" OR "b")`,
+/// so the field applies to all of them
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (field, search) = match self {
+            Query::Email(x) => ("email", x),
+            Query::IpAddress(x) => ("ip_address", x),
+            Query::Username(x) => ("username", x),
+            Query::Password(x) => ("password", x),
+            Query::HashedPassword(x) => ("hashed_password", x),
+            Query::Name(x) => ("name", x),
+            Query::Domain(x) => ("domain", x),
+            Query::Vin(x) => ("vin", x),
+            Query::Phone(x) => ("phone", x),
+            Query::Address(x) => ("address", x),
+        };
+        match search {
+            SearchType::Or(x) | SearchType::And(x) if x.len() > 1 => {
+                write!(f, "{field}:({search})")
+            }
+            _ => write!(f, "{field}:{search}"),
         }
     }
 }
 
+impl Query {
+    /// Create a query searching the provided field.
+    ///
+    /// Returns [None] if dehashed doesn't support searching the field.
+    pub fn for_field(field: EntryField, search: SearchType) -> Option<Query> {
+        Some(match field {
+            EntryField::Email => Query::Email(search),
+            EntryField::Username => Query::Username(search),
+            EntryField::Password => Query::Password(search),
+            EntryField::HashedPassword => Query::HashedPassword(search),
+            EntryField::IpAddress => Query::IpAddress(search),
+            EntryField::Name => Query::Name(search),
+            EntryField::Vin => Query::Vin(search),
+            EntryField::Address => Query::Address(search),
+            EntryField::Phone => Query::Phone(search),
+            EntryField::DatabaseName
+            | EntryField::HashType
+            | EntryField::Url
+            | EntryField::Social
+            | EntryField::Company
+            | EntryField::Dob
+            | EntryField::CryptocurrencyAddress
+            | EntryField::LicensePlate => return None,
+        })
+    }
+}
+
 /// The result of a search query
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -123,12 +185,135 @@ pub struct SearchResult {
     pub entries: Vec<SearchEntry>,
     /// The remaining balance
     pub balance: usize,
+    /// Warnings that occurred while converting the entries in [ConversionMode::Lenient]
+    #[serde(default)]
+    pub warnings: Vec<ConversionWarning>,
+    /// The total number of hits dehashed reported for the query.
+    ///
+    /// This may be larger than the number of [SearchResult::entries],
+    /// if not all pages could be fetched.
+    #[serde(default)]
+    pub total: usize,
+    /// Information about every page that was fetched
+    #[serde(default)]
+    pub pages: Vec<PageInfo>,
 }
 
-/// A single entry in a [SearchResult]
+impl SearchResult {
+    /// Check whether all hits reported by dehashed were fetched
+    pub fn is_complete(&self) -> bool {
+        self.entries.len() >= self.total
+    }
+}
+
+/// Split queries whose number of hits exceeds the [RESULT_WINDOW].
+///
+/// Every split combines the query with a prefix wildcard on [QuerySplitting::field],
+/// e.g. `domain:example.com` is split into `(domain:example.com) AND email:a*`,
+/// `(domain:example.com) AND email:b*` and so on.
+/// If a sub-query still exceeds the window, its prefix is extended by another character.
+///
+/// Only ascii letters and digits are used as prefixes, so entries whose field starts with
+/// another character or that are missing the field can only be found by the original query.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct QuerySplitting {
+    /// The field the prefixes are applied to, usually [EntryField::Email] or
+    /// [EntryField::Username].
+    ///
+    /// It must be searchable, see [Query::for_field].
+    pub field: EntryField,
+    /// The maximum length of the prefixes
+    pub max_depth: usize,
+}
+
+/// Information about a single page fetched while searching
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PageInfo {
+    /// The number of the page, starting at 1
+    pub page: usize,
+    /// The number of entries returned on this page
+    pub entries: usize,
+    /// The time dehashed reported it took to process the request
+    pub took: String,
+    /// The point in time the request was sent
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub requested_at: SystemTime,
+}
+
+/// The way the entries returned by dehashed are converted into [SearchEntry]s
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum ConversionMode {
+    /// Fail the whole search if a single entry is malformed
+    #[default]
+    Strict,
+    /// Keep malformed values as raw strings and skip entries that can't be converted at all.
+    ///
+    /// Every deviation is reported as [ConversionWarning] in [SearchResult::warnings].
+    Lenient,
+}
+
+/// A problem that occurred while converting an entry in [ConversionMode::Lenient]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ConversionWarning {
+    /// The raw ID of the affected entry
+    pub id: String,
+    /// The affected field, [None] if the entry was skipped completely
+    pub field: Option<EntryField>,
+    /// A description of the problem
+    pub message: String,
+}
+
+/// An ip address of a [SearchEntry]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum EntryIpAddr {
+    /// A valid ip address
+    Parsed(#[cfg_attr(feature = "utoipa", schema(value_type = String))] IpAddr),
+    /// The raw value, if it couldn't be parsed as ip address.
+    ///
+    /// This is only used in [ConversionMode::Lenient]
+    Raw(String),
+}
+
+impl EntryIpAddr {
+    /// Retrieve the ip address, if it could be parsed
+    pub fn parsed(&self) -> Option<IpAddr> {
+        match self {
+            EntryIpAddr::Parsed(x) => Some(*x),
+            EntryIpAddr::Raw(_) => None,
+        }
+    }
+}
+
+impl Display for EntryIpAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryIpAddr::Parsed(x) => write!(f, "{x}"),
+            EntryIpAddr::Raw(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+/// A single entry in a [SearchResult]
+///
+/// If dehashed returns multiple values for a field, only the first one is kept,
+/// use [DehashedApi::search_multi] to retrieve all of them.
+///
+/// The [Debug] and [Display] implementations redact the passwords,
+/// see [EntryField::is_secret].
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SearchEntry {
     /// ID of the entry
     pub id: u64,
@@ -140,8 +325,11 @@ pub struct SearchEntry {
     pub password: Option<String>,
     /// An hashed password, may be [None] if the result didn't include this field
     pub hashed_password: Option<String>,
-    /// An ip address, may be [None] if the result didn't include this field
-    pub ip_address: Option<IpAddr>,
+    /// An ip address, may be [None] if the result didn't include this field.
+    ///
+    /// Changed from `Option<IpAddr>` in 0.6.0, use [EntryIpAddr::Parsed] to match
+    /// the addresses that were parsed successfully.
+    pub ip_address: Option<EntryIpAddr>,
     /// A name, may be [None] if the result didn't include this field
     pub name: Option<String>,
     /// A vin, may be [None] if the result didn't include this field
@@ -152,74 +340,298 @@ pub struct SearchEntry {
     pub phone: Option<String>,
     /// A database name, may be [None] if the result didn't include this field
     pub database_name: Option<String>,
+    /// The type of the hashed password, may be [None] if the result didn't include this field
+    pub hash_type: Option<String>,
+    /// An url, may be [None] if the result didn't include this field
+    pub url: Option<String>,
+    /// A social media handle, may be [None] if the result didn't include this field
+    pub social: Option<String>,
+    /// A company, may be [None] if the result didn't include this field
+    pub company: Option<String>,
+    /// A date of birth, may be [None] if the result didn't include this field
+    pub dob: Option<String>,
+    /// A cryptocurrency address, may be [None] if the result didn't include this field
+    pub cryptocurrency_address: Option<String>,
+    /// A license plate, may be [None] if the result didn't include this field
+    pub license_plate: Option<String>,
+    /// All fields of the result that are not known to this crate
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A field of a [SearchEntry] which holds a value
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum EntryField {
+    /// [SearchEntry::email]
+    Email,
+    /// [SearchEntry::username]
+    Username,
+    /// [SearchEntry::password]
+    Password,
+    /// [SearchEntry::hashed_password]
+    HashedPassword,
+    /// [SearchEntry::ip_address]
+    IpAddress,
+    /// [SearchEntry::name]
+    Name,
+    /// [SearchEntry::vin]
+    Vin,
+    /// [SearchEntry::address]
+    Address,
+    /// [SearchEntry::phone]
+    Phone,
+    /// [SearchEntry::database_name]
+    DatabaseName,
+    /// [SearchEntry::hash_type]
+    HashType,
+    /// [SearchEntry::url]
+    Url,
+    /// [SearchEntry::social]
+    Social,
+    /// [SearchEntry::company]
+    Company,
+    /// [SearchEntry::dob]
+    Dob,
+    /// [SearchEntry::cryptocurrency_address]
+    CryptocurrencyAddress,
+    /// [SearchEntry::license_plate]
+    LicensePlate,
+}
+
+impl EntryField {
+    /// All fields in the order they are declared in [SearchEntry]
+    pub const ALL: [EntryField; 17] = [
+        EntryField::Email,
+        EntryField::Username,
+        EntryField::Password,
+        EntryField::HashedPassword,
+        EntryField::IpAddress,
+        EntryField::Name,
+        EntryField::Vin,
+        EntryField::Address,
+        EntryField::Phone,
+        EntryField::DatabaseName,
+        EntryField::HashType,
+        EntryField::Url,
+        EntryField::Social,
+        EntryField::Company,
+        EntryField::Dob,
+        EntryField::CryptocurrencyAddress,
+        EntryField::LicensePlate,
+    ];
+
+    /// The name of the field as used by dehashed
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryField::Email => "email",
+            EntryField::Username => "username",
+            EntryField::Password => "password",
+            EntryField::HashedPassword => "hashed_password",
+            EntryField::IpAddress => "ip_address",
+            EntryField::Name => "name",
+            EntryField::Vin => "vin",
+            EntryField::Address => "address",
+            EntryField::Phone => "phone",
+            EntryField::DatabaseName => "database_name",
+            EntryField::HashType => "hash_type",
+            EntryField::Url => "url",
+            EntryField::Social => "social",
+            EntryField::Company => "company",
+            EntryField::Dob => "dob",
+            EntryField::CryptocurrencyAddress => "cryptocurrency_address",
+            EntryField::LicensePlate => "license_plate",
+        }
+    }
+
+    /// Whether the field contains a secret, i.e. a password or password hash.
+    ///
+    /// The values of these fields are redacted in the [Debug] and [Display] output of
+    /// [SearchEntry] and [MultiSearchEntry].
+    pub fn is_secret(&self) -> bool {
+        matches!(self, EntryField::Password | EntryField::HashedPassword)
+    }
+}
+
+impl Display for EntryField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl SearchEntry {
+    /// Retrieve the value of a field, [None] if the result didn't include this field
+    pub fn get(&self, field: EntryField) -> Option<Cow<'_, str>> {
+        let value = match field {
+            EntryField::Email => &self.email,
+            EntryField::Username => &self.username,
+            EntryField::Password => &self.password,
+            EntryField::HashedPassword => &self.hashed_password,
+            EntryField::IpAddress => {
+                return self.ip_address.as_ref().map(|x| Cow::Owned(x.to_string()))
+            }
+            EntryField::Name => &self.name,
+            EntryField::Vin => &self.vin,
+            EntryField::Address => &self.address,
+            EntryField::Phone => &self.phone,
+            EntryField::DatabaseName => &self.database_name,
+            EntryField::HashType => &self.hash_type,
+            EntryField::Url => &self.url,
+            EntryField::Social => &self.social,
+            EntryField::Company => &self.company,
+            EntryField::Dob => &self.dob,
+            EntryField::CryptocurrencyAddress => &self.cryptocurrency_address,
+            EntryField::LicensePlate => &self.license_plate,
+        };
+        value.as_deref().map(Cow::Borrowed)
+    }
+}
+
+/// Prints all fields, replacing the values of secret fields and unknown fields with [REDACTED]
+impl Debug for SearchEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("SearchEntry");
+        s.field("id", &self.id);
+        for field in EntryField::ALL {
+            let value = self.get(field);
+            match value {
+                Some(_) if field.is_secret() => s.field(field.name(), &Some(Redacted)),
+                _ => s.field(field.name(), &value),
+            };
+        }
+        s.field("extra", &RedactedValues(&self.extra)).finish()
+    }
+}
+
+/// Prints the id and all present fields as `id: field=value, ...`,
+/// replacing the values of secret fields with [REDACTED]
+impl Display for SearchEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.id)?;
+        let mut first = true;
+        for field in EntryField::ALL {
+            let Some(value) = self.get(field) else {
+                continue;
+            };
+            let separator = if first { " " } else { ", " };
+            first = false;
+            if field.is_secret() {
+                write!(f, "{separator}{field}={REDACTED}")?;
+            } else {
+                write!(f, "{separator}{field}={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keep the first value of a field.
+///
+/// Further values are only available in a [MultiSearchEntry], see [DehashedApi::search_multi].
+fn first(values: Vec<String>) -> Option<String> {
+    values.into_iter().next()
 }
 
 impl TryFrom<Entry> for SearchEntry {
     type Error = DehashedError;
 
     fn try_from(value: Entry) -> Result<Self, Self::Error> {
-        Ok(Self {
-            id: value.id.parse()?,
-            email: if value.email.is_empty() {
-                None
-            } else {
-                Some(value.email)
-            },
-            username: if value.username.is_empty() {
-                None
-            } else {
-                Some(value.username)
-            },
-            password: if value.password.is_empty() {
-                None
-            } else {
-                Some(value.password)
-            },
-            hashed_password: if value.hashed_password.is_empty() {
-                None
-            } else {
-                Some(value.hashed_password)
-            },
-            ip_address: if value.ip_address.is_empty() {
-                None
-            } else {
-                Some(IpAddr::from_str(&value.ip_address)?)
-            },
-            name: if value.name.is_empty() {
-                None
-            } else {
-                Some(value.name)
-            },
-            vin: if value.vin.is_empty() {
-                None
-            } else {
-                Some(value.vin)
-            },
-            address: if value.address.is_empty() {
-                None
-            } else {
-                Some(value.address)
-            },
-            phone: if value.phone.is_empty() {
-                None
-            } else {
-                Some(value.phone)
-            },
-            database_name: if value.database_name.is_empty() {
-                None
-            } else {
-                Some(value.database_name)
-            },
-        })
+        let id = value.id.parse()?;
+        let ip_address = match value.ip_address.first() {
+            None => None,
+            Some(ip) => Some(EntryIpAddr::Parsed(IpAddr::from_str(ip)?)),
+        };
+        Ok(Self::from_parts(value, id, ip_address))
     }
 }
 
+impl SearchEntry {
+    /// Build an entry from the remaining fields of an [Entry] and the already parsed values
+    fn from_parts(value: Entry, id: u64, ip_address: Option<EntryIpAddr>) -> Self {
+        Self {
+            id,
+            email: first(value.email),
+            username: first(value.username),
+            password: first(value.password),
+            hashed_password: first(value.hashed_password),
+            ip_address,
+            name: first(value.name),
+            vin: first(value.vin),
+            address: first(value.address),
+            phone: first(value.phone),
+            database_name: first(value.database_name),
+            hash_type: first(value.hash_type),
+            url: first(value.url),
+            social: first(value.social),
+            company: first(value.company),
+            dob: first(value.dob),
+            cryptocurrency_address: first(value.cryptocurrency_address),
+            license_plate: first(value.license_plate),
+            extra: value.extra,
+        }
+    }
+
+    /// Convert an [Entry] without failing on malformed values.
+    ///
+    /// Returns [None] if the entry had to be skipped.
+    pub(crate) fn convert_lenient(
+        value: Entry,
+        warnings: &mut Vec<ConversionWarning>,
+    ) -> Option<Self> {
+        let id = match value.id.parse() {
+            Ok(id) => id,
+            Err(err) => {
+                warnings.push(ConversionWarning {
+                    id: value.id,
+                    field: None,
+                    message: format!("Skipped entry with invalid id: {err}"),
+                });
+                return None;
+            }
+        };
+
+        let ip_address = value
+            .ip_address
+            .first()
+            .map(|ip| match IpAddr::from_str(ip) {
+                Ok(ip) => EntryIpAddr::Parsed(ip),
+                Err(err) => {
+                    warnings.push(ConversionWarning {
+                        id: value.id.clone(),
+                        field: Some(EntryField::IpAddress),
+                        message: format!("Kept raw ip address {ip:?}: {err}"),
+                    });
+                    EntryIpAddr::Raw(ip.clone())
+                }
+            });
+
+        Some(Self::from_parts(value, id, ip_address))
+    }
+}
+
+/// The unconverted entries of all pages fetched for a query
+struct RawResult {
+    entries: Vec<Entry>,
+    balance: usize,
+    total: usize,
+    pages: Vec<PageInfo>,
+}
+
 /// The instance of the dehashed api
 #[derive(Clone, Debug)]
 pub struct DehashedApi {
     email: String,
-    api_key: String,
+    api_key: SecretString,
     client: Client,
+    base_url: String,
+    proxy: Option<String>,
+    request_delay: Duration,
+    budget: Arc<Mutex<BudgetState>>,
+    conversion_mode: ConversionMode,
+    query_splitting: Option<QuerySplitting>,
+    cassette: Option<Arc<Mutex<CassetteState>>>,
 }
 
 impl DehashedApi {
@@ -230,55 +642,276 @@ impl DehashedApi {
     /// - `api_key`: The api key for your account (found on your profile page)
     ///
     /// This method fails if the [Client] could not be constructed
-    pub fn new(email: String, api_key: String) -> Result<Self, DehashedError> {
+    pub fn new(email: String, mut api_key: String) -> Result<Self, DehashedError> {
+        let lowercase = SecretString::new(api_key.to_lowercase());
+        api_key.zeroize();
+
+        Ok(Self {
+            email,
+            client: Self::build_client(None, true)?,
+            base_url: DEFAULT_URL.to_string(),
+            proxy: None,
+            request_delay: DEFAULT_REQUEST_DELAY,
+            api_key: lowercase,
+            budget: Arc::new(Mutex::new(BudgetState::default())),
+            conversion_mode: ConversionMode::default(),
+            query_splitting: None,
+            cassette: None,
+        })
+    }
+
+    fn build_client(proxy: Option<&str>, https_only: bool) -> Result<Client, DehashedError> {
         let mut header_map = HeaderMap::new();
         header_map.insert("Accept", HeaderValue::from_static("application/json"));
 
-        let client = Client::builder()
+        let mut builder = Client::builder()
             .timeout(Duration::from_secs(10))
-            .https_only(true)
-            .default_headers(header_map)
-            .build()?;
+            .https_only(https_only)
+            .default_headers(header_map);
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        Ok(builder.build()?)
+    }
 
-        Ok(Self {
-            email,
-            client,
-            api_key: api_key.to_lowercase(),
-        })
+    /// Whether the [Client] for a base url must only make https connections.
+    ///
+    /// Plain http would send the credentials unencrypted, so it is only allowed for
+    /// loopback addresses, e.g. a local mock server.
+    fn https_only(url: &str) -> Result<bool, DehashedError> {
+        let Ok(parsed) = reqwest::Url::parse(url) else {
+            return Ok(true);
+        };
+        if parsed.scheme() != "http" {
+            return Ok(true);
+        }
+        let loopback = match parsed.host_str() {
+            Some(host) if host.eq_ignore_ascii_case("localhost") => true,
+            Some(host) => host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .is_ok_and(|x| x.is_loopback()),
+            None => false,
+        };
+        if loopback {
+            Ok(false)
+        } else {
+            Err(DehashedError::InsecureBaseUrl(url.to_string()))
+        }
     }
 
-    async fn raw_req(
-        &self,
-        size: usize,
-        page: usize,
-        query: String,
-    ) -> Result<Response, DehashedError> {
+    /// Set the url of the search endpoint.
+    ///
+    /// Defaults to [DEFAULT_URL].
+    /// Plain http is only allowed for loopback addresses, e.g. `http://127.0.0.1:8080/search`
+    /// for a local mock server, other http urls are rejected with
+    /// [DehashedError::InsecureBaseUrl].
+    /// This method fails as well if the [Client] could not be constructed
+    pub fn set_base_url(&mut self, url: String) -> Result<(), DehashedError> {
+        self.client = Self::build_client(self.proxy.as_deref(), Self::https_only(&url)?)?;
+        self.base_url = url;
+        Ok(())
+    }
+
+    /// Send all requests through a proxy, e.g. `socks5://127.0.0.1:9050`.
+    ///
+    /// [None] removes the proxy.
+    /// This method fails if the proxy url is invalid or the [Client] could not be constructed
+    pub fn set_proxy(&mut self, proxy: Option<&str>) -> Result<(), DehashedError> {
+        self.client = Self::build_client(proxy, Self::https_only(&self.base_url)?)?;
+        self.proxy = proxy.map(str::to_string);
+        Ok(())
+    }
+
+    /// Set the delay between two consecutive requests of a search or a `Scheduler`.
+    ///
+    /// Defaults to [DEFAULT_REQUEST_DELAY], keep in mind that dehashed bans every account
+    /// that is doing more than 5 req / s.
+    pub fn set_request_delay(&mut self, delay: Duration) {
+        self.request_delay = delay;
+    }
+
+    /// Retrieve the delay between two consecutive requests
+    pub fn request_delay(&self) -> Duration {
+        self.request_delay
+    }
+
+    /// The maximum number of credits a single search may cost.
+    ///
+    /// Every query fetches up to [MAX_PAGES] pages, with [QuerySplitting] every possible
+    /// sub-query is accounted for as well.
+    pub(crate) fn max_search_cost(&self) -> usize {
+        let queries = match self.query_splitting {
+            None => 1,
+            Some(splitting) => (1..=splitting.max_depth.max(1)).fold(1usize, |n, depth| {
+                n.saturating_add(SPLIT_ALPHABET.len().saturating_pow(depth as u32))
+            }),
+        };
+        queries.saturating_mul(MAX_PAGES)
+    }
+
+    /// Set the [QuerySplitting] that is used for queries exceeding the [RESULT_WINDOW].
+    ///
+    /// Splitting is disabled by default.
+    /// Keep in mind that every sub-query costs credits.
+    ///
+    /// Returns [DehashedError::UnsearchableField] if [QuerySplitting::field] can't be searched.
+    pub fn set_query_splitting(
+        &mut self,
+        splitting: Option<QuerySplitting>,
+    ) -> Result<(), DehashedError> {
+        if let Some(splitting) = splitting {
+            if Query::for_field(splitting.field, SearchType::Simple(String::new())).is_none() {
+                return Err(DehashedError::UnsearchableField(splitting.field));
+            }
+        }
+        self.query_splitting = splitting;
+        Ok(())
+    }
+
+    /// Set the [ConversionMode] that is used for the entries of a search.
+    ///
+    /// Defaults to [ConversionMode::Strict].
+    pub fn set_conversion_mode(&mut self, mode: ConversionMode) {
+        self.conversion_mode = mode;
+    }
+
+    /// Set the [Budget] that limits the spending of credits.
+    ///
+    /// The budget is shared between all clones of this instance, including the ones
+    /// used by a `Scheduler`.
+    pub fn set_budget(&self, budget: Budget) {
+        self.budget.lock().unwrap().set_budget(budget);
+    }
+
+    /// Retrieve the currently active [Budget]
+    pub fn budget(&self) -> Budget {
+        self.budget.lock().unwrap().budget().clone()
+    }
+
+    /// Retrieve the remaining balance of the account.
+    ///
+    /// This is the balance reported by the last response of dehashed,
+    /// it is [None] if no request was made yet.
+    pub fn remaining_balance(&self) -> Option<usize> {
+        self.budget.lock().unwrap().balance()
+    }
+
+    pub(crate) fn set_cassette(&mut self, cassette: Option<Arc<Mutex<CassetteState>>>) {
+        self.cassette = cassette;
+    }
+
+    pub(crate) fn cassette_state(&self) -> Option<&Arc<Mutex<CassetteState>>> {
+        self.cassette.as_ref()
+    }
+
+    /// Whether the requests are served from a replayed cassette
+    fn replaying(&self) -> bool {
+        self.cassette
+            .as_ref()
+            .is_some_and(|x| x.lock().unwrap().mode() == CassetteMode::Replay)
+    }
+
+    /// The delay between two requests, which is skipped while replaying a cassette
+    pub(crate) fn effective_delay(&self) -> Duration {
+        if self.replaying() {
+            Duration::ZERO
+        } else {
+            self.request_delay
+        }
+    }
+
+    /// Send a request to dehashed or replay it from the cassette
+    async fn send(&self, request: RecordedRequest) -> Result<(u16, String), DehashedError> {
+        if let Some(cassette) = &self.cassette {
+            let mut cassette = cassette.lock().unwrap();
+            if cassette.mode() == CassetteMode::Replay {
+                let response = cassette.replay(&request)?;
+                return Ok((response.status, response.body));
+            }
+        }
+
         let res = self
             .client
-            .get(URL)
-            .basic_auth(&self.email, Some(&self.api_key))
+            .get(&self.base_url)
+            .basic_auth(&self.email, Some(self.api_key.expose()))
             .query(&[
-                ("size", size.to_string()),
-                ("query", query),
-                ("page", page.to_string()),
+                ("size", request.size.to_string()),
+                ("query", request.query.clone()),
+                ("page", request.page.to_string()),
             ])
             .send()
             .await?;
+        let status = res.status().as_u16();
+        let body = res.text().await?;
+
+        if let Some(cassette) = &self.cassette {
+            cassette
+                .lock()
+                .unwrap()
+                .record(request, status, &body, self.api_key.expose())?;
+        }
+        Ok((status, body))
+    }
+
+    /// Reserve the credits of up to `credits` requests.
+    ///
+    /// Replayed requests cost nothing, so nothing is reserved while replaying a cassette.
+    fn reserve(&self, credits: usize) -> Result<Reservation, DehashedError> {
+        if self.replaying() {
+            Ok(Reservation::none())
+        } else {
+            Reservation::new(&self.budget, credits)
+        }
+    }
+
+    /// Fetch the balance if it is required by [Budget::min_balance], but not known yet
+    async fn probe_balance(&self) -> Result<(), DehashedError> {
+        let unknown = {
+            let budget = self.budget.lock().unwrap();
+            budget.budget().min_balance.is_some() && budget.balance().is_none()
+        };
+        if unknown && !self.replaying() {
+            self.balance().await?;
+            #[cfg(feature = "tokio")]
+            sleep(self.effective_delay()).await;
+        }
+        Ok(())
+    }
+
+    async fn raw_req(
+        &self,
+        reservation: &mut Reservation,
+        size: usize,
+        page: usize,
+        query: String,
+    ) -> Result<Response, DehashedError> {
+        // Replayed requests cost nothing, so they don't update the balance
+        let replaying = self.replaying();
+        reservation.spend()?;
 
-        let status = res.status();
-        if status == StatusCode::from_u16(302).unwrap() {
+        let (status, raw) = self.send(RecordedRequest { query, page, size }).await?;
+        if status == StatusCode::FOUND {
             Err(DehashedError::InvalidQuery)
-        } else if status == StatusCode::from_u16(400).unwrap() {
+        } else if status == StatusCode::BAD_REQUEST {
             Err(DehashedError::RateLimited)
-        } else if status == StatusCode::from_u16(401).unwrap() {
+        } else if status == StatusCode::UNAUTHORIZED {
             Err(DehashedError::Unauthorized)
-        } else if status == StatusCode::from_u16(200).unwrap() {
-            let raw = res.text().await?;
-
-            match serde_json::from_str(&raw) {
-                Ok(result) => Ok(result),
+        } else if status == StatusCode::OK {
+            match serde_json::from_str::<Response>(&raw) {
+                Ok(result) => {
+                    if !replaying {
+                        self.budget.lock().unwrap().update_balance(result.balance);
+                    }
+                    Ok(result)
+                }
                 Err(err) => {
-                    error!("Error deserializing data: {err}. Raw data: {raw}");
+                    // The body contains leaked credentials, so it must not end up in the logs
+                    error!(
+                        "Error deserializing data: {err}. Body of {} bytes",
+                        raw.len()
+                    );
                     Err(DehashedError::Unknown)
                 }
             }
@@ -293,39 +926,193 @@ impl DehashedApi {
     /// that is doing more than 5 req / s.
     ///
     /// This method will take care of pagination and will delay requests if necessary.
+    ///
+    /// Dehashed only allows paging through the first [RESULT_WINDOW] results of a query.
+    /// If [DehashedApi::set_query_splitting] is configured, larger queries are split
+    /// into narrower sub-queries, otherwise the search stops at the end of the window.
+    /// Compare [SearchResult::total] with the number of entries to detect this.
     pub async fn search(&self, query: Query) -> Result<SearchResult, DehashedError> {
-        let q = query.to_string();
-        debug!("Query: {q}");
+        self.search_raw(query.to_string()).await
+    }
+
+    /// Query the API with a raw query string, e.g. `email:test@example.com`.
+    ///
+    /// The query is passed to dehashed as-is, so reserved characters have to be escaped
+    /// by the caller. Apart from that, this behaves like [DehashedApi::search].
+    pub async fn search_raw(&self, q: String) -> Result<SearchResult, DehashedError> {
+        let raw = self.collect(q).await?;
 
         let mut search_result = SearchResult {
+            entries: vec![],
+            balance: raw.balance,
+            warnings: vec![],
+            total: raw.total,
+            pages: raw.pages,
+        };
+        for entry in raw.entries {
+            match self.conversion_mode {
+                ConversionMode::Strict => search_result.entries.push(entry.try_into()?),
+                ConversionMode::Lenient => search_result.entries.extend(
+                    SearchEntry::convert_lenient(entry, &mut search_result.warnings),
+                ),
+            }
+        }
+        Ok(search_result)
+    }
+
+    /// Query the API and keep all values of every field.
+    ///
+    /// Newer responses of dehashed contain multiple values for fields like `email`,
+    /// of which a [SearchEntry] only keeps the first one.
+    /// Apart from returning [MultiSearchEntry]s, this behaves like [DehashedApi::search].
+    pub async fn search_multi(&self, query: Query) -> Result<MultiSearchResult, DehashedError> {
+        let raw = self.collect(query.to_string()).await?;
+
+        let mut search_result = MultiSearchResult {
+            entries: vec![],
+            balance: raw.balance,
+            warnings: vec![],
+            total: raw.total,
+            pages: raw.pages,
+        };
+        for entry in raw.entries {
+            let id = match (entry.id.parse::<u64>(), self.conversion_mode) {
+                (Ok(id), _) => id,
+                (Err(err), ConversionMode::Strict) => return Err(err.into()),
+                (Err(err), ConversionMode::Lenient) => {
+                    search_result.warnings.push(ConversionWarning {
+                        id: entry.id,
+                        field: None,
+                        message: format!("Skipped entry with invalid id: {err}"),
+                    });
+                    continue;
+                }
+            };
+            search_result
+                .entries
+                .push(MultiSearchEntry::from_entry(entry, id));
+        }
+        Ok(search_result)
+    }
+
+    /// Fetch all entries of a raw query string, splitting it if configured.
+    ///
+    /// The credits of all requests the search may send are reserved before the first one.
+    async fn collect(&self, q: String) -> Result<RawResult, DehashedError> {
+        debug!("Query: {q}");
+
+        self.probe_balance().await?;
+        let mut reservation = self.reserve(self.max_search_cost())?;
+
+        let mut raw = self.fetch(&mut reservation, &q).await?;
+        let Some(splitting) = self.query_splitting else {
+            return Ok(raw);
+        };
+        if raw.total <= RESULT_WINDOW {
+            return Ok(raw);
+        }
+
+        let total = raw.total;
+        let field = splitting.field.name();
+        let mut prefixes: VecDeque<String> = SPLIT_ALPHABET.chars().map(String::from).collect();
+        while let Some(prefix) = prefixes.pop_front() {
+            #[cfg(feature = "tokio")]
+            sleep(self.effective_delay()).await;
+
+            let sub_query = format!("({q}) AND {field}:{prefix}*");
+            debug!("Sub query: {sub_query}");
+            let sub_raw = self.fetch(&mut reservation, &sub_query).await?;
+
+            if sub_raw.total > RESULT_WINDOW && prefix.len() < splitting.max_depth {
+                prefixes.extend(SPLIT_ALPHABET.chars().map(|c| format!("{prefix}{c}")));
+            }
+            raw.entries.extend(sub_raw.entries);
+            raw.pages.extend(sub_raw.pages);
+            raw.balance = sub_raw.balance;
+        }
+        let mut seen = HashSet::new();
+        raw.entries.retain(|x| seen.insert(x.id.clone()));
+        raw.total = total;
+
+        Ok(raw)
+    }
+
+    /// Fetch all pages of a raw query string within the [RESULT_WINDOW]
+    async fn fetch(
+        &self,
+        reservation: &mut Reservation,
+        q: &str,
+    ) -> Result<RawResult, DehashedError> {
+        let mut raw = RawResult {
             entries: vec![],
             balance: 0,
+            total: 0,
+            pages: vec![],
         };
-        for page in 1.. {
-            let res = self.raw_req(10_000, page, q.clone()).await?;
+        for page in 1..=MAX_PAGES {
+            let requested_at = SystemTime::now();
+            let res = self
+                .raw_req(reservation, PAGE_SIZE, page, q.to_string())
+                .await?;
 
             if !res.success {
                 error!("Success field in response is set to false");
                 return Err(DehashedError::Unknown);
             }
 
-            if let Some(entries) = res.entries {
-                for entry in entries {
-                    search_result.entries.push(entry.try_into()?)
-                }
-            }
+            raw.pages.push(PageInfo {
+                page,
+                entries: res.entries.as_ref().map_or(0, Vec::len),
+                took: res.took,
+                requested_at,
+            });
+            raw.entries.extend(res.entries.into_iter().flatten());
+            raw.balance = res.balance;
+            raw.total = res.total;
 
-            search_result.balance = res.balance;
-
-            if res.total < page * 10_000 {
+            if res.total < page * PAGE_SIZE {
+                break;
+            }
+            if page == MAX_PAGES {
+                warn!(
+                    "Query exceeds the result window, fetched {} of {} entries",
+                    raw.entries.len(),
+                    res.total
+                );
                 break;
             }
 
             #[cfg(feature = "tokio")]
-            sleep(Duration::from_millis(200)).await;
+            sleep(self.effective_delay()).await;
         }
 
-        Ok(search_result)
+        Ok(raw)
+    }
+
+    /// Retrieve the current balance of the account.
+    ///
+    /// This sends the cheapest request possible, a query for a single entry of a fixed term,
+    /// so it costs a single credit.
+    /// The returned balance is also available using [DehashedApi::remaining_balance] afterwards.
+    pub async fn balance(&self) -> Result<usize, DehashedError> {
+        let mut reservation = self.reserve(1)?;
+        let res = self
+            .raw_req(&mut reservation, 1, 1, PROBE_QUERY.to_string())
+            .await?;
+        Ok(res.balance)
+    }
+
+    /// Check whether the provided credentials are accepted by dehashed.
+    ///
+    /// Returns `false` if dehashed responded with [DehashedError::Unauthorized],
+    /// other errors are passed through.
+    /// Like [DehashedApi::balance], this costs a single credit.
+    pub async fn verify_credentials(&self) -> Result<bool, DehashedError> {
+        match self.balance().await {
+            Ok(_) => Ok(true),
+            Err(DehashedError::Unauthorized) => Ok(false),
+            Err(err) => Err(err),
+        }
     }
 
     /// Start a new scheduler.
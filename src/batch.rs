@@ -0,0 +1,234 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "tokio")]
+use tokio::time::sleep;
+
+use crate::api::{EntryField, Query, SearchEntry, SearchResult, SearchType};
+use crate::error::DehashedError;
+use crate::multi::MultiSearchEntry;
+use crate::DehashedApi;
+#[cfg(feature = "tokio")]
+use crate::Scheduler;
+
+/// Options for a batch search
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BatchOptions {
+    /// The maximum length of a single query string
+    pub max_query_length: usize,
+}
+
+impl Default for BatchOptions {
+    /// Queries with up to 1,000 characters
+    fn default() -> Self {
+        Self {
+            max_query_length: 1_000,
+        }
+    }
+}
+
+/// The outcome of a single value of a batch search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum BatchOutcome {
+    /// The entries containing the value
+    Hits(Vec<SearchEntry>),
+    /// No entry contains the value
+    NoHits,
+    /// The query containing the value failed
+    Failed(String),
+}
+
+/// A value of a batch search and its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BatchItem {
+    /// The value as provided
+    pub value: String,
+    /// The outcome
+    pub outcome: BatchOutcome,
+}
+
+/// The result of a batch search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BatchResult {
+    /// One item per distinct value, in the order the values were provided
+    pub items: Vec<BatchItem>,
+    /// The number of queries that were executed
+    pub queries: usize,
+    /// The remaining balance
+    pub balance: usize,
+}
+
+impl DehashedApi {
+    /// Search a list of values of the same field with as few queries as possible.
+    ///
+    /// The values are packed into [SearchType::Or] queries of exact values, as long as the
+    /// query stays within [BatchOptions::max_query_length].
+    /// If the hits of a query exceed the [RESULT_WINDOW](crate::RESULT_WINDOW),
+    /// the query is split in half and retried.
+    ///
+    /// The entries are assigned back to the values they contain,
+    /// comparing case-insensitively.
+    /// The queries are delayed by [DehashedApi::request_delay].
+    /// Fails with [DehashedError::InvalidQuery] if the field can't be searched.
+    pub async fn batch_search<I, S>(
+        &self,
+        values: I,
+        field: EntryField,
+        options: &BatchOptions,
+    ) -> Result<BatchResult, DehashedError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        batch_with(values, field, options, self.effective_delay(), |query| {
+            self.search(query)
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Scheduler {
+    /// Search a list of values of the same field with as few queries as possible.
+    ///
+    /// See [DehashedApi::batch_search], the queries are delayed by the scheduler.
+    pub async fn batch_search<I, S>(
+        &self,
+        values: I,
+        field: EntryField,
+        options: &BatchOptions,
+    ) -> Result<BatchResult, DehashedError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        batch_with(values, field, options, Duration::ZERO, |query| {
+            self.search(query)
+        })
+        .await
+    }
+}
+
+/// Normalise a value to match it against entries
+fn normalise(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Build the query for a chunk of values
+fn chunk_query(field: EntryField, chunk: &[String]) -> Option<Query> {
+    Query::for_field(
+        field,
+        SearchType::Or(chunk.iter().cloned().map(SearchType::Exact).collect()),
+    )
+}
+
+/// Run a batch search with a custom search function, waiting `delay` between two queries
+pub(crate) async fn batch_with<I, S, F, Fut>(
+    values: I,
+    field: EntryField,
+    options: &BatchOptions,
+    #[cfg_attr(not(feature = "tokio"), allow(unused_variables))] delay: Duration,
+    mut search: F,
+) -> Result<BatchResult, DehashedError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+    F: FnMut(Query) -> Fut,
+    Fut: Future<Output = Result<SearchResult, DehashedError>>,
+{
+    // Index of the item of every normalised value
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut items = vec![];
+    for value in values {
+        let value: String = value.into();
+        let normalised = normalise(&value);
+        if normalised.is_empty() || index.contains_key(&normalised) {
+            continue;
+        }
+        index.insert(normalised, items.len());
+        items.push(BatchItem {
+            value,
+            outcome: BatchOutcome::NoHits,
+        });
+    }
+
+    let mut chunks = VecDeque::new();
+    let mut chunk: Vec<String> = vec![];
+    for item in &items {
+        chunk.push(item.value.trim().to_string());
+        let query = chunk_query(field, &chunk).ok_or(DehashedError::InvalidQuery)?;
+        if chunk.len() > 1 && query.to_string().len() > options.max_query_length {
+            let last = chunk.pop().unwrap_or_default();
+            chunks.push_back(std::mem::replace(&mut chunk, vec![last]));
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push_back(chunk);
+    }
+
+    let mut result = BatchResult {
+        items,
+        queries: 0,
+        balance: 0,
+    };
+    while let Some(chunk) = chunks.pop_front() {
+        let query = chunk_query(field, &chunk).ok_or(DehashedError::InvalidQuery)?;
+        #[cfg(feature = "tokio")]
+        if result.queries > 0 {
+            sleep(delay).await;
+        }
+        debug!("Batch query with {} values", chunk.len());
+        result.queries += 1;
+
+        let res = match search(query).await {
+            Ok(res) => res,
+            Err(err) if err.is_fatal() => return Err(err),
+            Err(err) => {
+                warn!("Batch query failed: {err}");
+                for value in &chunk {
+                    result.items[index[&normalise(value)]].outcome =
+                        BatchOutcome::Failed(err.to_string());
+                }
+                continue;
+            }
+        };
+        result.balance = res.balance;
+
+        if !res.is_complete() && chunk.len() > 1 {
+            let (a, b) = chunk.split_at(chunk.len() / 2);
+            chunks.push_front(b.to_vec());
+            chunks.push_front(a.to_vec());
+            continue;
+        }
+
+        for entry in res.entries {
+            let multi = MultiSearchEntry::from(entry.clone());
+            for value in multi.values(field) {
+                let Some(i) = index.get(&normalise(value)) else {
+                    continue;
+                };
+                match &mut result.items[*i].outcome {
+                    BatchOutcome::Hits(hits) => {
+                        if !hits.iter().any(|x| x.id == entry.id) {
+                            hits.push(entry.clone());
+                        }
+                    }
+                    outcome => *outcome = BatchOutcome::Hits(vec![entry.clone()]),
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,328 @@
+//! Command line interface for the dehashed api
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use dehashed_rs::{
+    BatchOptions, BatchOutcome, BatchResult, Config, ConversionMode, CsvWriter, DehashedApi,
+    DehashedError, EntryField, JsonlWriter, Query, SearchResult, SearchType,
+};
+
+/// Exit code if the search returned no hits
+const EXIT_NO_HITS: u8 = 3;
+/// Exit code if the credentials were rejected
+const EXIT_UNAUTHORIZED: u8 = 4;
+/// Exit code if the account got rate limited
+const EXIT_RATE_LIMITED: u8 = 5;
+/// Exit code for all other errors
+const EXIT_ERROR: u8 = 1;
+
+/// Search the dehashed api from the terminal
+#[derive(Parser)]
+#[clap(
+    version,
+    about,
+    after_help = "\
+Credentials are read from a profile of the config file,
+DEHASHED_EMAIL and DEHASHED_API_KEY take precedence.
+
+Exit codes:
+  0  Success
+  1  Other error, or a value of a batch failed
+  2  Invalid usage
+  3  No hits
+  4  Invalid credentials
+  5  Rate limited"
+)]
+struct Cli {
+    /// The output format
+    #[clap(short, long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+
+    /// The config file containing the profiles,
+    /// defaults to `$XDG_CONFIG_HOME/dehashed/config.toml`
+    #[clap(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// The profile of the config file to use
+    #[clap(short, long, global = true)]
+    profile: Option<String>,
+
+    /// Keep malformed values instead of failing the whole search
+    #[clap(long, global = true)]
+    lenient: bool,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search for a single value or a raw query string
+    Search {
+        /// The field to search
+        #[clap(long, value_parser = parse_field, requires = "value")]
+        field: Option<EntryField>,
+
+        /// The value to search for
+        #[clap(long, requires = "field")]
+        value: Option<String>,
+
+        /// Search for the exact value
+        #[clap(long)]
+        exact: bool,
+
+        /// A raw query string, e.g. `domain:example.com`
+        #[clap(long, conflicts_with_all = ["field", "value"], required_unless_present = "field")]
+        query: Option<String>,
+    },
+    /// Search every line of a file as value of the same field
+    Batch {
+        /// The field to search
+        #[clap(long, value_parser = parse_field)]
+        field: EntryField,
+
+        /// The file containing one value per line, `-` reads from stdin
+        file: PathBuf,
+    },
+    /// Show the remaining balance of the account
+    Balance,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+    Jsonl,
+    Csv,
+}
+
+fn parse_field(field: &str) -> Result<EntryField, String> {
+    EntryField::ALL
+        .into_iter()
+        .find(|x| x.name() == field)
+        .ok_or_else(|| format!("Unknown field {field}"))
+}
+
+fn load_api(cli: &Cli) -> Result<DehashedApi, DehashedError> {
+    let config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::load_default()?,
+    };
+    let mut api = config.profile(cli.profile.as_deref())?.build()?;
+    if cli.lenient {
+        api.set_conversion_mode(ConversionMode::Lenient);
+    }
+    Ok(api)
+}
+
+fn exit_code(err: &DehashedError) -> u8 {
+    match err {
+        DehashedError::Unauthorized => EXIT_UNAUTHORIZED,
+        DehashedError::RateLimited => EXIT_RATE_LIMITED,
+        _ => EXIT_ERROR,
+    }
+}
+
+/// Print a result as table, only showing the columns that contain values
+fn print_table(result: &SearchResult, out: &mut impl Write) -> io::Result<()> {
+    const MAX_WIDTH: usize = 40;
+
+    let fields: Vec<EntryField> = EntryField::ALL
+        .into_iter()
+        .filter(|field| result.entries.iter().any(|x| x.get(*field).is_some()))
+        .collect();
+    let mut rows = vec![std::iter::once("id".to_string())
+        .chain(fields.iter().map(|x| x.name().to_string()))
+        .collect::<Vec<_>>()];
+    for entry in &result.entries {
+        rows.push(
+            std::iter::once(entry.id.to_string())
+                .chain(fields.iter().map(|field| {
+                    let value = entry.get(*field).unwrap_or_default().replace('\n', " ");
+                    if value.chars().count() > MAX_WIDTH {
+                        let mut value: String = value.chars().take(MAX_WIDTH - 1).collect();
+                        value.push('…');
+                        value
+                    } else {
+                        value
+                    }
+                }))
+                .collect(),
+        );
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| rows.iter().map(|x| x[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:width$}"))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())?;
+    }
+    writeln!(
+        out,
+        "\n{} of {} entries, remaining balance: {}",
+        result.entries.len(),
+        result.total,
+        result.balance
+    )
+}
+
+fn print_result(result: &SearchResult, query: String, format: Format) -> Result<(), DehashedError> {
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Table => print_table(result, &mut out)?,
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, result)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => {
+            JsonlWriter::write_archive(&mut out, query, result)?;
+        }
+        Format::Csv => {
+            let mut writer = CsvWriter::new(&mut out);
+            writer.write_result(result)?;
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
+
+fn print_batch(result: &BatchResult, format: Format) -> Result<(), DehashedError> {
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Table | Format::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(if matches!(format, Format::Table) {
+                    b'\t'
+                } else {
+                    b','
+                })
+                .from_writer(out);
+            writer.write_record(["value", "outcome", "hits"])?;
+            for item in &result.items {
+                let (outcome, hits) = match &item.outcome {
+                    BatchOutcome::Hits(hits) => (
+                        "hits".to_string(),
+                        hits.iter()
+                            .map(|x| x.id.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                    BatchOutcome::NoHits => ("no hits".to_string(), String::new()),
+                    BatchOutcome::Failed(err) => (format!("failed: {err}"), String::new()),
+                };
+                writer.write_record([item.value.as_str(), &outcome, &hits])?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut out, result)?;
+            writeln!(out)?;
+        }
+        Format::Jsonl => {
+            for item in &result.items {
+                serde_json::to_writer(&mut out, item)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<u8, DehashedError> {
+    let api = load_api(&cli)?;
+
+    match cli.command {
+        Command::Search {
+            field,
+            value,
+            exact,
+            query,
+        } => {
+            let query = match (query, field, value) {
+                (Some(query), _, _) => query,
+                (None, Some(field), Some(value)) => {
+                    let search = if exact {
+                        SearchType::Exact(value)
+                    } else {
+                        SearchType::Simple(value)
+                    };
+                    match Query::for_field(field, search) {
+                        Some(query) => query.to_string(),
+                        None => {
+                            eprintln!("The field {field} can't be searched");
+                            return Ok(EXIT_ERROR);
+                        }
+                    }
+                }
+                _ => unreachable!("Enforced by clap"),
+            };
+
+            let result = api.search_raw(query.clone()).await?;
+            print_result(&result, query, cli.format)?;
+            Ok(if result.entries.is_empty() {
+                EXIT_NO_HITS
+            } else {
+                0
+            })
+        }
+        Command::Batch { field, file } => {
+            let reader: Box<dyn BufRead> = if file.as_os_str() == "-" {
+                Box::new(io::stdin().lock())
+            } else {
+                Box::new(BufReader::new(fs::File::open(file)?))
+            };
+            let values = reader.lines().collect::<Result<Vec<_>, _>>()?;
+
+            let result = api
+                .batch_search(values, field, &BatchOptions::default())
+                .await?;
+            print_batch(&result, cli.format)?;
+            let failed = result
+                .items
+                .iter()
+                .any(|x| matches!(x.outcome, BatchOutcome::Failed(_)));
+            let hits = result
+                .items
+                .iter()
+                .any(|x| matches!(x.outcome, BatchOutcome::Hits(_)));
+            Ok(if failed {
+                EXIT_ERROR
+            } else if hits {
+                0
+            } else {
+                EXIT_NO_HITS
+            })
+        }
+        Command::Balance => {
+            let balance = api.balance().await?;
+            match cli.format {
+                Format::Json | Format::Jsonl => {
+                    println!("{}", serde_json::json!({ "balance": balance }))
+                }
+                Format::Table | Format::Csv => println!("{balance}"),
+            }
+            Ok(0)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
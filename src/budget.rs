@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::DehashedError;
+
+/// Limits that protect the balance of the account from being drained.
+///
+/// Every request to dehashed costs at least one credit.
+/// A search reserves the credits of all requests it may send before sending the first one,
+/// if one of the limits would be crossed, the search fails with
+/// [DehashedError::BudgetExceeded] without sending any request.
+///
+/// [Budget::min_balance] requires the balance of the account to be known.
+/// If no request was made yet, the balance is fetched first,
+/// see [DehashedApi::balance](crate::DehashedApi::balance).
+#[derive(Clone, Debug)]
+pub struct Budget {
+    /// The balance that must remain on the account
+    pub min_balance: Option<usize>,
+    /// The maximum number of requests that may be sent in a single window
+    pub max_requests: Option<usize>,
+    /// The maximum number of credits that may be spent in a single window
+    pub max_credits: Option<usize>,
+    /// The duration of the sliding window [Budget::max_requests] and
+    /// [Budget::max_credits] are applied to
+    pub window: Duration,
+}
+
+impl Default for Budget {
+    /// A budget without any limits and a window of one hour
+    fn default() -> Self {
+        Self {
+            min_balance: None,
+            max_requests: None,
+            max_credits: None,
+            window: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// The limit of a [Budget] that would have been crossed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BudgetLimit {
+    /// [Budget::min_balance]
+    MinBalance,
+    /// [Budget::max_requests]
+    Requests,
+    /// [Budget::max_credits]
+    Credits,
+}
+
+/// A request that was sent in the current window
+#[derive(Debug)]
+struct Spending {
+    at: Instant,
+    credits: usize,
+}
+
+/// The shared state of a [Budget]
+#[derive(Debug, Default)]
+pub(crate) struct BudgetState {
+    budget: Budget,
+    balance: Option<usize>,
+    spent: VecDeque<Spending>,
+    /// The credits that are reserved by running searches, but not spent yet
+    reserved: usize,
+}
+
+impl BudgetState {
+    pub(crate) fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    pub(crate) fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
+    pub(crate) fn balance(&self) -> Option<usize> {
+        self.balance
+    }
+
+    /// Check the limits for up to `credits` new requests and reserve them
+    /// if they are within all limits.
+    ///
+    /// The [Budget::min_balance] is only checked if the balance is known.
+    pub(crate) fn reserve(&mut self, credits: usize) -> Result<(), DehashedError> {
+        let now = Instant::now();
+        while self
+            .spent
+            .front()
+            .is_some_and(|s| now.duration_since(s.at) >= self.budget.window)
+        {
+            self.spent.pop_front();
+        }
+
+        let pending = self.reserved.saturating_add(credits);
+        if let (Some(min), Some(balance)) = (self.budget.min_balance, self.balance) {
+            if balance.saturating_sub(pending) < min {
+                return Err(DehashedError::BudgetExceeded(BudgetLimit::MinBalance));
+            }
+        }
+        if let Some(max) = self.budget.max_requests {
+            if self.spent.len().saturating_add(pending) > max {
+                return Err(DehashedError::BudgetExceeded(BudgetLimit::Requests));
+            }
+        }
+        if let Some(max) = self.budget.max_credits {
+            let spent = self.spent.iter().map(|s| s.credits).sum::<usize>();
+            if spent.saturating_add(pending) > max {
+                return Err(DehashedError::BudgetExceeded(BudgetLimit::Credits));
+            }
+        }
+
+        self.reserved = pending;
+        Ok(())
+    }
+
+    /// Account for a request whose credit was reserved before
+    pub(crate) fn spend(&mut self) {
+        self.reserved = self.reserved.saturating_sub(1);
+        self.spent.push_back(Spending {
+            at: Instant::now(),
+            credits: 1,
+        });
+    }
+
+    /// Release reserved credits that weren't spent
+    pub(crate) fn release(&mut self, credits: usize) {
+        self.reserved = self.reserved.saturating_sub(credits);
+    }
+
+    /// Update the balance with the value reported by dehashed.
+    ///
+    /// If the balance dropped by more than a single credit since the last update,
+    /// the difference is accounted to the last request.
+    pub(crate) fn update_balance(&mut self, balance: usize) {
+        if let (Some(prev), Some(last)) = (self.balance, self.spent.back_mut()) {
+            last.credits = last.credits.max(prev.saturating_sub(balance));
+        }
+        self.balance = Some(balance);
+    }
+}
+
+/// Credits reserved for a search, the unspent ones are released when dropped
+#[derive(Debug)]
+pub(crate) struct Reservation {
+    /// [None] if nothing has to be accounted, e.g. while replaying a cassette
+    state: Option<Arc<Mutex<BudgetState>>>,
+    remaining: usize,
+}
+
+impl Reservation {
+    /// Reserve up to `credits` requests of a [BudgetState]
+    pub(crate) fn new(
+        state: &Arc<Mutex<BudgetState>>,
+        credits: usize,
+    ) -> Result<Self, DehashedError> {
+        state.lock().unwrap().reserve(credits)?;
+        Ok(Self {
+            state: Some(state.clone()),
+            remaining: credits,
+        })
+    }
+
+    /// A reservation that doesn't account for anything
+    pub(crate) fn none() -> Self {
+        Self {
+            state: None,
+            remaining: 0,
+        }
+    }
+
+    /// Account for a request that is about to be sent.
+    ///
+    /// If all reserved credits are spent already, another one has to be reserved.
+    pub(crate) fn spend(&mut self) -> Result<(), DehashedError> {
+        if let Some(state) = &self.state {
+            let mut state = state.lock().unwrap();
+            if self.remaining == 0 {
+                state.reserve(1)?;
+            } else {
+                self.remaining -= 1;
+            }
+            state.spend();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            state.lock().unwrap().release(self.remaining);
+        }
+    }
+}
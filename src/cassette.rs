@@ -0,0 +1,234 @@
+use std::fmt::{Debug, Formatter};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::error::DehashedError;
+use crate::DehashedApi;
+
+/// A request sent to dehashed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RecordedRequest {
+    /// The query string
+    pub query: String,
+    /// The requested page
+    pub page: usize,
+    /// The requested page size
+    pub size: usize,
+}
+
+/// The response of dehashed to a [RecordedRequest]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RecordedResponse {
+    /// The http status code
+    pub status: u16,
+    /// The raw body
+    pub body: String,
+}
+
+/// Only prints the length of the body, as it contains leaked credentials
+impl Debug for RecordedResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordedResponse")
+            .field("status", &self.status)
+            .field("body", &format_args!("<{} bytes>", self.body.len()))
+            .finish()
+    }
+}
+
+/// A request and its response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Interaction {
+    /// The request
+    pub request: RecordedRequest,
+    /// The response
+    pub response: RecordedResponse,
+}
+
+/// A recording of the interactions with dehashed.
+///
+/// The authentication of the requests is not recorded.
+/// Keep in mind that the bodies contain the leaked data of the responses.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Cassette {
+    /// All interactions in the order they happened
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Load a cassette from a json file
+    pub fn load(path: &Path) -> Result<Self, DehashedError> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Save the cassette as json file.
+    ///
+    /// The file contains leaked credentials, so on unix it is only accessible by its owner.
+    pub fn save(&self, path: &Path) -> Result<(), DehashedError> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+        // The mode only applies to new files, an existing one may be more permissive
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Whether requests are recorded or replayed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// The cassette of a [DehashedApi], shared between all clones
+#[derive(Debug)]
+pub(crate) struct CassetteState {
+    mode: CassetteMode,
+    cassette: Cassette,
+    /// Whether the interaction at the same index was already replayed
+    used: Vec<bool>,
+    /// The file the cassette is saved to after every recorded interaction
+    path: Option<PathBuf>,
+}
+
+impl CassetteState {
+    pub(crate) fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Append an interaction, removing the api key from the body in case it is echoed.
+    ///
+    /// If the recording is persisted, the cassette is saved afterwards.
+    pub(crate) fn record(
+        &mut self,
+        request: RecordedRequest,
+        status: u16,
+        body: &str,
+        key: &str,
+    ) -> Result<(), DehashedError> {
+        let body = if key.is_empty() {
+            body.to_string()
+        } else {
+            body.replace(key, "<redacted>")
+        };
+        self.cassette.interactions.push(Interaction {
+            request,
+            response: RecordedResponse { status, body },
+        });
+        if let Some(path) = &self.path {
+            self.cassette.save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Retrieve the first response to an equal request that wasn't replayed yet
+    pub(crate) fn replay(
+        &mut self,
+        request: &RecordedRequest,
+    ) -> Result<RecordedResponse, DehashedError> {
+        let index = self
+            .cassette
+            .interactions
+            .iter()
+            .zip(&self.used)
+            .position(|(x, used)| !used && x.request == *request);
+        match index {
+            Some(i) => {
+                self.used[i] = true;
+                Ok(self.cassette.interactions[i].response.clone())
+            }
+            None => {
+                error!("No recorded interaction for request {request:?}");
+                Err(DehashedError::UnmatchedRequest(request.clone()))
+            }
+        }
+    }
+}
+
+impl DehashedApi {
+    /// Start recording all requests and responses.
+    ///
+    /// The recording is kept in memory only, it is shared between all clones of this
+    /// instance and replaces an active recording or replay.
+    /// Retrieve it with [DehashedApi::cassette] or [DehashedApi::stop_cassette] and
+    /// use [Cassette::save] to persist it, or use [DehashedApi::start_recording_to].
+    pub fn start_recording(&mut self) {
+        self.set_cassette(Some(Arc::new(Mutex::new(CassetteState {
+            mode: CassetteMode::Record,
+            cassette: Cassette::default(),
+            used: vec![],
+            path: None,
+        }))));
+    }
+
+    /// Start recording all requests and responses to a file.
+    ///
+    /// Like [DehashedApi::start_recording], but the cassette is saved to `path` right away
+    /// and after every recorded interaction, see [Cassette::save].
+    /// A request whose interaction couldn't be saved fails.
+    pub fn start_recording_to(&mut self, path: PathBuf) -> Result<(), DehashedError> {
+        let cassette = Cassette::default();
+        cassette.save(&path)?;
+        self.set_cassette(Some(Arc::new(Mutex::new(CassetteState {
+            mode: CassetteMode::Record,
+            cassette,
+            used: vec![],
+            path: Some(path),
+        }))));
+        Ok(())
+    }
+
+    /// Serve all requests from a cassette instead of dehashed.
+    ///
+    /// Every recorded interaction is replayed once, in the order of the recording.
+    /// A request without an unused recorded interaction fails with
+    /// [DehashedError::UnmatchedRequest].
+    /// Replayed requests are neither delayed nor counted against the [Budget](crate::Budget).
+    pub fn replay(&mut self, cassette: Cassette) {
+        let used = vec![false; cassette.interactions.len()];
+        self.set_cassette(Some(Arc::new(Mutex::new(CassetteState {
+            mode: CassetteMode::Replay,
+            cassette,
+            used,
+            path: None,
+        }))));
+    }
+
+    /// Stop recording or replaying and return the cassette
+    pub fn stop_cassette(&mut self) -> Option<Cassette> {
+        let cassette = self.cassette();
+        self.set_cassette(None);
+        cassette
+    }
+
+    /// Retrieve the cassette that is currently recorded or replayed
+    pub fn cassette(&self) -> Option<Cassette> {
+        self.cassette_state()
+            .map(|x| x.lock().unwrap().cassette.clone())
+    }
+}
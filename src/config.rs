@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::budget::Budget;
+use crate::error::DehashedError;
+use crate::secret::SecretString;
+use crate::DehashedApi;
+
+/// The name of the profile that is used if neither a name nor
+/// [Config::default_profile] is provided
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named set of credentials and settings for a [DehashedApi]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The mail address that is used for authentication,
+    /// overridden by `DEHASHED_EMAIL`
+    pub email: Option<String>,
+    /// The api key of the account, overridden by `DEHASHED_API_KEY`.
+    ///
+    /// The key is never serialized.
+    #[serde(skip_serializing)]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>))]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    pub api_key: Option<SecretString>,
+    /// The url of the search endpoint, overridden by `DEHASHED_BASE_URL`,
+    /// see [DehashedApi::set_base_url]
+    pub base_url: Option<String>,
+    /// The proxy all requests are sent through, overridden by `DEHASHED_PROXY`,
+    /// see [DehashedApi::set_proxy]
+    pub proxy: Option<String>,
+    /// The delay between two requests in milliseconds,
+    /// see [DehashedApi::set_request_delay]
+    pub request_delay_ms: Option<u64>,
+    /// See [Budget::min_balance]
+    pub min_balance: Option<usize>,
+    /// The maximum number of requests per hour, see [Budget::max_requests]
+    pub max_requests: Option<usize>,
+    /// The maximum number of credits per hour, see [Budget::max_credits]
+    pub max_credits: Option<usize>,
+    /// A directory applications may use to cache results, overridden by `DEHASHED_CACHE_DIR`.
+    ///
+    /// This is not used by [DehashedApi] itself.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Profile {
+    /// Override the values of the profile with the `DEHASHED_*` environment variables
+    pub fn apply_env(&mut self) {
+        self.apply_overrides(|name| std::env::var(name).ok());
+    }
+
+    pub(crate) fn apply_overrides(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if let Some(x) = var("DEHASHED_API_KEY").filter(|x| !x.is_empty()) {
+            self.api_key = Some(SecretString::new(x));
+        }
+        let strings = [
+            ("DEHASHED_EMAIL", &mut self.email),
+            ("DEHASHED_BASE_URL", &mut self.base_url),
+            ("DEHASHED_PROXY", &mut self.proxy),
+        ];
+        for (name, value) in strings {
+            if let Some(x) = var(name).filter(|x| !x.is_empty()) {
+                *value = Some(x);
+            }
+        }
+        if let Some(x) = var("DEHASHED_CACHE_DIR").filter(|x| !x.is_empty()) {
+            self.cache_dir = Some(PathBuf::from(x));
+        }
+    }
+
+    /// Build a [DehashedApi] from this profile.
+    ///
+    /// Fails with [DehashedError::MissingCredentials] if the email or api key is missing.
+    pub fn build(&self) -> Result<DehashedApi, DehashedError> {
+        let (Some(email), Some(api_key)) = (&self.email, &self.api_key) else {
+            return Err(DehashedError::MissingCredentials);
+        };
+
+        let mut api = DehashedApi::new(email.clone(), api_key.expose().to_string())?;
+        if let Some(base_url) = &self.base_url {
+            api.set_base_url(base_url.clone())?;
+        }
+        if self.proxy.is_some() {
+            api.set_proxy(self.proxy.as_deref())?;
+        }
+        if let Some(delay) = self.request_delay_ms {
+            api.set_request_delay(Duration::from_millis(delay));
+        }
+        api.set_budget(Budget {
+            min_balance: self.min_balance,
+            max_requests: self.max_requests,
+            max_credits: self.max_credits,
+            ..Budget::default()
+        });
+        Ok(api)
+    }
+}
+
+/// The content of a config file.
+///
+/// ```toml
+/// default_profile = "work"
+///
+/// [profiles.work]
+/// email = "alice@example.com"
+/// api_key = "..."
+/// request_delay_ms = 250
+/// max_credits = 100
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The profile that is used if no name is provided, defaults to [DEFAULT_PROFILE]
+    pub default_profile: Option<String>,
+    /// All profiles by name
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// Whether [Config::load_default] didn't find a file
+    #[serde(skip)]
+    pub(crate) missing_file: bool,
+}
+
+impl Config {
+    /// The default location of the config file.
+    ///
+    /// This is `$DEHASHED_CONFIG` if set, otherwise `dehashed/config.toml` in
+    /// `$XDG_CONFIG_HOME` or `$HOME/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("DEHASHED_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))?;
+        Some(dir.join("dehashed").join("config.toml"))
+    }
+
+    /// Parse a config from a string
+    pub fn parse(content: &str) -> Result<Self, DehashedError> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Load a config file.
+    ///
+    /// As the file contains credentials, loading fails with [DehashedError::InsecureConfig]
+    /// on unix if the file is accessible by other users than its owner.
+    pub fn load(path: &Path) -> Result<Self, DehashedError> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if fs::metadata(path)?.permissions().mode() & 0o077 != 0 {
+                return Err(DehashedError::InsecureConfig(path.to_path_buf()));
+            }
+        }
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Load the config file from [Config::default_path].
+    ///
+    /// If the file doesn't exist, an empty config is returned, so every profile can still
+    /// be provided using environment variables, see [Config::profile].
+    pub fn load_default() -> Result<Self, DehashedError> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load(&path),
+            _ => Ok(Self {
+                missing_file: true,
+                ..Self::default()
+            }),
+        }
+    }
+
+    /// Retrieve a profile with the environment variables applied, see [Profile::apply_env].
+    ///
+    /// If `name` is [None], [Config::default_profile] is used.
+    /// Fails with [DehashedError::UnknownProfile] if a profile was requested explicitly,
+    /// but doesn't exist. A missing default profile is treated as empty,
+    /// as is every profile if [Config::load_default] didn't find a file.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, DehashedError> {
+        let mut profile = match name {
+            Some(name) if self.missing_file => self.profiles.get(name).cloned().unwrap_or_default(),
+            Some(name) => self
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| DehashedError::UnknownProfile(name.to_string()))?,
+            None => {
+                let name = self.default_profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+                self.profiles.get(name).cloned().unwrap_or_default()
+            }
+        };
+        profile.apply_env();
+        Ok(profile)
+    }
+}
+
+impl DehashedApi {
+    /// Create a new instance from a profile of the config file at [Config::default_path].
+    ///
+    /// See [Config::profile] and [Profile::build]
+    pub fn from_profile(name: &str) -> Result<Self, DehashedError> {
+        Config::load_default()?.profile(Some(name))?.build()
+    }
+}
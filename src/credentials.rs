@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{SearchEntry, SearchResult};
+use crate::error::DehashedError;
+use crate::hashes::HashKind;
+use crate::secret::Redacted;
+
+/// Values that are used as placeholder for missing data in leaks
+const JUNK: [&str; 7] = ["null", "none", "nil", "n/a", "undefined", "-", "(null)"];
+
+/// The identity of a [Credential]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Identity {
+    /// A normalised email address
+    Email(String),
+    /// A username
+    Username(String),
+}
+
+impl Identity {
+    /// Retrieve the value of the identity
+    pub fn value(&self) -> &str {
+        match self {
+            Identity::Email(x) | Identity::Username(x) => x,
+        }
+    }
+}
+
+/// The secret of a [Credential]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Secret {
+    /// A plaintext password
+    Plaintext(String),
+    /// A hashed password
+    Hash {
+        /// The hash
+        value: String,
+        /// The most likely algorithm of the hash, see [HashKind::detect]
+        kind: Option<HashKind>,
+    },
+}
+
+impl Secret {
+    /// Retrieve the value of the secret
+    pub fn value(&self) -> &str {
+        match self {
+            Secret::Plaintext(x) => x,
+            Secret::Hash { value, .. } => value,
+        }
+    }
+}
+
+/// Prints the kind of the secret, replacing its value with [REDACTED](crate::REDACTED)
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Secret::Plaintext(_) => f.debug_tuple("Plaintext").field(&Redacted).finish(),
+            Secret::Hash { kind, .. } => f
+                .debug_struct("Hash")
+                .field("value", &Redacted)
+                .field("kind", kind)
+                .finish(),
+        }
+    }
+}
+
+/// A pair of identity and secret extracted from a [SearchEntry]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Credential {
+    /// The identity
+    pub identity: Identity,
+    /// The secret
+    pub secret: Secret,
+    /// ID of the entry the credential was extracted from
+    pub entry_id: u64,
+    /// The database the entry originates from
+    pub database_name: Option<String>,
+}
+
+/// Formats the credential as `identity:secret` line of a combolist
+impl Display for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.identity.value(), self.secret.value())
+    }
+}
+
+/// Options for extracting [Credential]s
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExtractOptions {
+    /// Fold gmail addresses by removing dots and `+` suffixes from the local part,
+    /// e.g. `J.Doe+news@googlemail.com` becomes `jdoe@gmail.com`
+    pub fold_gmail: bool,
+    /// Include hashed passwords as secrets
+    pub include_hashes: bool,
+}
+
+/// Normalise an email address.
+///
+/// The address is trimmed and converted to lower case, see [ExtractOptions::fold_gmail]
+/// for additional folding.
+pub fn normalise_email(email: &str, fold_gmail: bool) -> String {
+    let email = email.trim().to_lowercase();
+    if !fold_gmail {
+        return email;
+    }
+    match email.rsplit_once('@') {
+        Some((local, "gmail.com" | "googlemail.com")) => {
+            let local = local.split('+').next().unwrap_or_default().replace('.', "");
+            format!("{local}@gmail.com")
+        }
+        _ => email,
+    }
+}
+
+/// Check whether a value is a placeholder for missing data, e.g. empty or `NULL`
+pub(crate) fn is_junk(value: &str) -> bool {
+    let value = value.trim();
+    value.is_empty() || JUNK.iter().any(|x| value.eq_ignore_ascii_case(x))
+}
+
+impl SearchEntry {
+    /// Extract all credentials of this entry.
+    ///
+    /// The identity is the email, or the username if the email is missing.
+    /// Placeholder values like empty strings or `NULL` are dropped.
+    pub fn credentials(&self, options: &ExtractOptions) -> Vec<Credential> {
+        let identity = match (&self.email, &self.username) {
+            (Some(email), _) if !is_junk(email) => {
+                Identity::Email(normalise_email(email, options.fold_gmail))
+            }
+            (_, Some(username)) if !is_junk(username) => {
+                Identity::Username(username.trim().to_string())
+            }
+            _ => return vec![],
+        };
+
+        let mut secrets = vec![];
+        if let Some(password) = self.password.as_deref().filter(|x| !is_junk(x)) {
+            secrets.push(Secret::Plaintext(password.to_string()));
+        }
+        if options.include_hashes {
+            if let Some(hash) = self.hashed_password.as_deref().filter(|x| !is_junk(x)) {
+                let hash = hash.trim();
+                secrets.push(Secret::Hash {
+                    value: hash.to_string(),
+                    kind: HashKind::detect(hash),
+                });
+            }
+        }
+
+        secrets
+            .into_iter()
+            .map(|secret| Credential {
+                identity: identity.clone(),
+                secret,
+                entry_id: self.id,
+                database_name: self.database_name.clone(),
+            })
+            .collect()
+    }
+}
+
+impl SearchResult {
+    /// Extract the deduplicated credentials of all entries.
+    ///
+    /// See [SearchEntry::credentials], the first occurrence of every pair is kept.
+    pub fn credentials(&self, options: &ExtractOptions) -> Vec<Credential> {
+        let mut seen = HashSet::new();
+        self.entries
+            .iter()
+            .flat_map(|x| x.credentials(options))
+            .filter(|x| seen.insert((x.identity.clone(), x.secret.clone())))
+            .collect()
+    }
+}
+
+/// Write credentials as combolist with one `identity:secret` per line
+pub fn write_combolist<W: Write>(
+    credentials: &[Credential],
+    mut writer: W,
+) -> Result<(), DehashedError> {
+    for credential in credentials {
+        writeln!(writer, "{credential}")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
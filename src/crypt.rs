@@ -0,0 +1,196 @@
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroizing;
+
+/// The alphabet of the base64 variant used by crypt
+const ALPHABET: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The order in which the bytes of a MD5-crypt digest are encoded
+const MD5_ORDER: [usize; 16] = [0, 6, 12, 1, 7, 13, 2, 8, 14, 3, 9, 15, 4, 10, 5, 11];
+
+/// The order in which the bytes of a SHA256-crypt digest are encoded
+const SHA256_ORDER: [usize; 32] = [
+    0, 10, 20, 21, 1, 11, 12, 22, 2, 3, 13, 23, 24, 4, 14, 15, 25, 5, 6, 16, 26, 27, 7, 17, 18, 28,
+    8, 9, 19, 29, 31, 30,
+];
+
+/// The order in which the bytes of a SHA512-crypt digest are encoded
+const SHA512_ORDER: [usize; 64] = [
+    0, 21, 42, 22, 43, 1, 44, 2, 23, 3, 24, 45, 25, 46, 4, 47, 5, 26, 6, 27, 48, 28, 49, 7, 50, 8,
+    29, 9, 30, 51, 31, 52, 10, 53, 11, 32, 12, 33, 54, 34, 55, 13, 56, 14, 35, 15, 36, 57, 37, 58,
+    16, 59, 17, 38, 18, 39, 60, 40, 61, 19, 62, 20, 41, 63,
+];
+
+/// The number of rounds of SHA-crypt if the hash doesn't specify them
+const DEFAULT_ROUNDS: usize = 5_000;
+/// The minimum number of rounds of SHA-crypt
+const MIN_ROUNDS: usize = 1_000;
+/// The maximum number of rounds of SHA-crypt that is checked.
+///
+/// The rounds are taken from leaked hashes, so they can't be trusted.
+/// SHA-crypt allows up to 999,999,999 rounds, which would take minutes to check.
+const MAX_ROUNDS: usize = 100_000;
+
+/// Check a candidate against a MD5-crypt (`$1$`), SHA256-crypt (`$5$`) or
+/// SHA512-crypt (`$6$`) hash, [None] if the hash is malformed or uses more than
+/// [MAX_ROUNDS] rounds
+pub(crate) fn verify_crypt(hash: &str, candidate: &str) -> Option<bool> {
+    let (id, rest) = hash.strip_prefix('$')?.split_once('$')?;
+    let (rounds, rest) = match rest.strip_prefix("rounds=") {
+        Some(rest) => {
+            let (rounds, rest) = rest.split_once('$')?;
+            (Some(rounds.parse::<usize>().ok()?), rest)
+        }
+        None => (None, rest),
+    };
+    let (salt, checksum) = rest.split_once('$')?;
+
+    let password = candidate.as_bytes();
+    let sha_rounds = rounds.unwrap_or(DEFAULT_ROUNDS).max(MIN_ROUNDS);
+    if sha_rounds > MAX_ROUNDS {
+        return None;
+    }
+    let computed = match id {
+        "1" if rounds.is_none() => md5_crypt(password, salt.as_bytes()),
+        "5" => sha_crypt::<Sha256>(password, salt.as_bytes(), sha_rounds, &SHA256_ORDER),
+        "6" => sha_crypt::<Sha512>(password, salt.as_bytes(), sha_rounds, &SHA512_ORDER),
+        _ => return None,
+    };
+    Some(computed == checksum)
+}
+
+/// Compute the checksum of MD5-crypt as implemented by FreeBSD
+fn md5_crypt(password: &[u8], salt: &[u8]) -> String {
+    let salt = &salt[..salt.len().min(8)];
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut ctx = Md5::new()
+        .chain_update(password)
+        .chain_update(b"$1$")
+        .chain_update(salt);
+    for chunk in password.chunks(alternate.len()) {
+        ctx.update(&alternate[..chunk.len()]);
+    }
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 {
+            ctx.update([0]);
+        } else {
+            ctx.update(&password[..1]);
+        }
+        i >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    for i in 0..1_000 {
+        let mut ctx = Md5::new();
+        if i % 2 == 1 {
+            ctx.update(password);
+        } else {
+            ctx.update(&digest[..]);
+        }
+        if i % 3 != 0 {
+            ctx.update(salt);
+        }
+        if i % 7 != 0 {
+            ctx.update(password);
+        }
+        if i % 2 == 1 {
+            ctx.update(&digest[..]);
+        } else {
+            ctx.update(password);
+        }
+        digest = ctx.finalize();
+    }
+    encode(&digest, &MD5_ORDER)
+}
+
+/// Compute the checksum of SHA-crypt as specified by Ulrich Drepper
+fn sha_crypt<D: Digest>(password: &[u8], salt: &[u8], rounds: usize, order: &[usize]) -> String {
+    let salt = &salt[..salt.len().min(16)];
+    let alternate = D::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut ctx = D::new().chain_update(password).chain_update(salt);
+    for chunk in password.chunks(alternate.len()) {
+        ctx.update(&alternate[..chunk.len()]);
+    }
+    let mut i = password.len();
+    while i > 0 {
+        if i & 1 == 1 {
+            ctx.update(&alternate[..]);
+        } else {
+            ctx.update(password);
+        }
+        i >>= 1;
+    }
+    let mut digest = ctx.finalize();
+
+    let mut ctx = D::new();
+    for _ in 0..password.len() {
+        ctx.update(password);
+    }
+    let p_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        ctx.finalize()
+            .iter()
+            .cycle()
+            .take(password.len())
+            .copied()
+            .collect(),
+    );
+    let mut ctx = D::new();
+    for _ in 0..16 + digest[0] as usize {
+        ctx.update(salt);
+    }
+    let s_bytes: Vec<u8> = ctx
+        .finalize()
+        .iter()
+        .cycle()
+        .take(salt.len())
+        .copied()
+        .collect();
+
+    for i in 0..rounds {
+        let mut ctx = D::new();
+        if i % 2 == 1 {
+            ctx.update(&p_bytes);
+        } else {
+            ctx.update(&digest[..]);
+        }
+        if i % 3 != 0 {
+            ctx.update(&s_bytes);
+        }
+        if i % 7 != 0 {
+            ctx.update(&p_bytes);
+        }
+        if i % 2 == 1 {
+            ctx.update(&digest[..]);
+        } else {
+            ctx.update(&p_bytes);
+        }
+        digest = ctx.finalize();
+    }
+    encode(&digest, order)
+}
+
+/// Encode the bytes of a digest in the given order, using groups of three bytes
+fn encode(digest: &[u8], order: &[usize]) -> String {
+    let mut encoded = String::new();
+    for group in order.chunks(3) {
+        let mut value = group
+            .iter()
+            .fold(0u32, |value, i| value << 8 | digest[*i] as u32);
+        for _ in 0..=group.len() {
+            encoded.push(ALPHABET[(value & 0x3f) as usize] as char);
+            value >>= 6;
+        }
+    }
+    encoded
+}
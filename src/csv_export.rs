@@ -0,0 +1,127 @@
+use std::io::Write;
+
+use csv::{QuoteStyle, WriterBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{EntryField, SearchEntry, SearchResult};
+use crate::error::DehashedError;
+
+/// A column of a csv export
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum CsvColumn {
+    /// [SearchEntry::id]
+    Id,
+    /// Any field of a [SearchEntry]
+    Field(EntryField),
+}
+
+impl CsvColumn {
+    /// The name of the column used in the header
+    pub fn name(&self) -> &'static str {
+        match self {
+            CsvColumn::Id => "id",
+            CsvColumn::Field(field) => field.name(),
+        }
+    }
+
+    /// All columns, the id followed by the fields in the order of [EntryField::ALL]
+    pub fn all() -> Vec<CsvColumn> {
+        std::iter::once(CsvColumn::Id)
+            .chain(EntryField::ALL.into_iter().map(CsvColumn::Field))
+            .collect()
+    }
+}
+
+/// A writer that streams [SearchEntry]s as csv.
+///
+/// The header is written before the first entry.
+/// Fields that are [None] are written as empty cell.
+/// Cells containing delimiters, quotes or line breaks are quoted.
+pub struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    columns: Vec<CsvColumn>,
+    escape_formulas: bool,
+    header_written: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Create a new writer including all columns, see [CsvColumn::all]
+    pub fn new(writer: W) -> Self {
+        Self::with_columns(writer, CsvColumn::all())
+    }
+
+    /// Create a new writer which only writes the provided columns in the provided order
+    pub fn with_columns(writer: W, columns: Vec<CsvColumn>) -> Self {
+        Self {
+            writer: WriterBuilder::new()
+                .quote_style(QuoteStyle::Necessary)
+                .from_writer(writer),
+            columns,
+            escape_formulas: false,
+            header_written: false,
+        }
+    }
+
+    /// Prefix cells starting with `=`, `+`, `-`, `@` or a tab with a `'`.
+    ///
+    /// This prevents spreadsheet applications from interpreting leaked values as formulas,
+    /// but alters the values. Disabled by default.
+    pub fn set_formula_escaping(&mut self, escape: bool) {
+        self.escape_formulas = escape;
+    }
+
+    fn write_header(&mut self) -> Result<(), DehashedError> {
+        if !self.header_written {
+            self.writer
+                .write_record(self.columns.iter().map(CsvColumn::name))?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+
+    /// Write a single entry
+    pub fn write_entry(&mut self, entry: &SearchEntry) -> Result<(), DehashedError> {
+        self.write_header()?;
+
+        let record: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let value = match column {
+                    CsvColumn::Id => entry.id.to_string(),
+                    CsvColumn::Field(field) => entry
+                        .get(*field)
+                        .map(|x| x.into_owned())
+                        .unwrap_or_default(),
+                };
+                if self.escape_formulas && value.starts_with(['=', '+', '-', '@', '\t']) {
+                    format!("'{value}")
+                } else {
+                    value
+                }
+            })
+            .collect();
+        self.writer.write_record(&record)?;
+        Ok(())
+    }
+
+    /// Write all entries of a result
+    pub fn write_result(&mut self, result: &SearchResult) -> Result<(), DehashedError> {
+        for entry in &result.entries {
+            self.write_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the writer and return the underlying writer.
+    ///
+    /// The header is written even if no entry was written.
+    pub fn finish(mut self) -> Result<W, DehashedError> {
+        self.write_header()?;
+        self.writer
+            .into_inner()
+            .map_err(|err| DehashedError::IoError(err.into_error()))
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+use crate::api::SearchResult;
+use crate::error::DehashedError;
+use crate::secret::SecretString;
+
+/// The first bytes of every encrypted archive
+const MAGIC: &[u8; 8] = b"DHSRENC\0";
+/// The current version of the archive format
+const VERSION: u8 = 1;
+/// [ArchiveKey::Raw] is used as is
+const KDF_RAW: u8 = 0;
+/// [ArchiveKey::Passphrase] is derived using Argon2id
+const KDF_ARGON2ID: u8 = 1;
+/// The length of the salt of the key derivation
+const SALT_LEN: usize = 16;
+/// The length of the XChaCha20Poly1305 nonce
+const NONCE_LEN: usize = 24;
+/// The maximum memory cost in KiB accepted from an archive header
+const MAX_MEMORY_COST: u32 = 1024 * 1024;
+/// The maximum number of iterations accepted from an archive header
+const MAX_TIME_COST: u32 = 64;
+/// The maximum degree of parallelism accepted from an archive header
+const MAX_PARALLELISM: u32 = 64;
+
+/// The key of an encrypted archive
+#[derive(Clone)]
+pub enum ArchiveKey {
+    /// A passphrase the key is derived from using Argon2id with a random salt
+    Passphrase(SecretString),
+    /// A random 256 bit key
+    Raw(Zeroizing<[u8; 32]>),
+}
+
+impl Debug for ArchiveKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveKey::Passphrase(_) => write!(f, "Passphrase(<redacted>)"),
+            ArchiveKey::Raw(_) => write!(f, "Raw(<redacted>)"),
+        }
+    }
+}
+
+impl ArchiveKey {
+    /// Create a key from a passphrase
+    pub fn passphrase(passphrase: String) -> Self {
+        Self::Passphrase(SecretString::new(passphrase))
+    }
+
+    /// Create a key from 32 random bytes
+    pub fn raw(key: [u8; 32]) -> Self {
+        Self::Raw(Zeroizing::new(key))
+    }
+
+    /// Generate a random 256 bit key
+    pub fn generate() -> Self {
+        let mut key = Zeroizing::new([0; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        Self::Raw(key)
+    }
+}
+
+/// Derive the key of a passphrase
+fn derive(
+    passphrase: &str,
+    salt: &[u8],
+    params: Params,
+) -> Result<Zeroizing<[u8; 32]>, DehashedError> {
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|_| DehashedError::InvalidArchive)?;
+    Ok(key)
+}
+
+impl SearchResult {
+    /// Write the result as encrypted archive.
+    ///
+    /// The result is serialized as json and encrypted with XChaCha20Poly1305.
+    /// The archive starts with a header containing the format version, the key derivation
+    /// parameters and the nonce, which is authenticated as well.
+    pub fn write_encrypted<W: Write>(
+        &self,
+        mut writer: W,
+        key: &ArchiveKey,
+    ) -> Result<(), DehashedError> {
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        let key = match key {
+            ArchiveKey::Raw(key) => {
+                header.push(KDF_RAW);
+                key.clone()
+            }
+            ArchiveKey::Passphrase(passphrase) => {
+                let params = Params::default();
+                let mut salt = [0; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+
+                header.push(KDF_ARGON2ID);
+                header.extend(params.m_cost().to_le_bytes());
+                header.extend(params.t_cost().to_le_bytes());
+                header.extend(params.p_cost().to_le_bytes());
+                header.extend(salt);
+                derive(passphrase.expose(), &salt, params)?
+            }
+        };
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        header.extend(nonce);
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(self)?);
+        let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| DehashedError::Unknown)?;
+
+        writer.write_all(&header)?;
+        writer.write_all(&ciphertext)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read an archive written by [SearchResult::write_encrypted].
+    ///
+    /// Fails with [DehashedError::DecryptionFailed] if the key is wrong or the archive
+    /// was modified.
+    pub fn read_encrypted<R: Read>(mut reader: R, key: &ArchiveKey) -> Result<Self, DehashedError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+
+        let mut rest = data.as_slice();
+        let mut take = |n: usize| -> Result<&[u8], DehashedError> {
+            if rest.len() < n {
+                return Err(DehashedError::InvalidArchive);
+            }
+            let (head, tail) = rest.split_at(n);
+            rest = tail;
+            Ok(head)
+        };
+
+        if take(MAGIC.len())? != MAGIC {
+            return Err(DehashedError::InvalidArchive);
+        }
+        let version = take(1)?[0];
+        if version != VERSION {
+            return Err(DehashedError::UnsupportedArchiveVersion(version));
+        }
+        let kdf = take(1)?[0];
+        let key = match (kdf, key) {
+            (KDF_RAW, ArchiveKey::Raw(key)) => key.clone(),
+            (KDF_ARGON2ID, ArchiveKey::Passphrase(passphrase)) => {
+                let mut cost = || -> Result<u32, DehashedError> {
+                    let bytes = take(4)?;
+                    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                };
+                let (m_cost, t_cost, p_cost) = (cost()?, cost()?, cost()?);
+                // The header is untrusted, so the cost of the derivation is limited
+                if m_cost > MAX_MEMORY_COST || t_cost > MAX_TIME_COST || p_cost > MAX_PARALLELISM {
+                    return Err(DehashedError::InvalidArchive);
+                }
+                let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+                    .map_err(|_| DehashedError::InvalidArchive)?;
+                derive(passphrase.expose(), take(SALT_LEN)?, params)?
+            }
+            (KDF_RAW | KDF_ARGON2ID, _) => return Err(DehashedError::DecryptionFailed),
+            _ => return Err(DehashedError::InvalidArchive),
+        };
+        let nonce = *XNonce::from_slice(take(NONCE_LEN)?);
+
+        let header_len = data.len() - rest.len();
+        let (header, ciphertext) = data.split_at(header_len);
+        let plaintext = Zeroizing::new(
+            XChaCha20Poly1305::new(key.as_ref().into())
+                .decrypt(
+                    &nonce,
+                    Payload {
+                        msg: ciphertext,
+                        aad: header,
+                    },
+                )
+                .map_err(|_| DehashedError::DecryptionFailed)?,
+        );
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
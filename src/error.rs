@@ -1,8 +1,18 @@
 use std::fmt::{Display, Formatter};
 use std::net::AddrParseError;
+#[cfg(feature = "config")]
+use std::path::PathBuf;
 
-/// The common error type of this crate
+use crate::api::EntryField;
+use crate::budget::BudgetLimit;
+use crate::cassette::RecordedRequest;
+
+/// The common error type of this crate.
+///
+/// Some variants only exist if the corresponding feature is enabled,
+/// so the enum is non-exhaustive to keep the features additive.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DehashedError {
     /// Error that are caused by reqwest
     ReqwestError(reqwest::Error),
@@ -18,6 +28,43 @@ pub enum DehashedError {
     ParseIntError(std::num::ParseIntError),
     /// An error occurred while parsing an ip addr field
     ParseAddrError(AddrParseError),
+    /// The request would have crossed a limit of the configured [Budget](crate::Budget)
+    BudgetExceeded(BudgetLimit),
+    /// An io error occurred while reading or writing results
+    IoError(std::io::Error),
+    /// An error occurred while serializing or deserializing json
+    JsonError(serde_json::Error),
+    /// An archive is missing its header or the header is malformed
+    InvalidArchive,
+    /// The scheduler was stopped before the request could be processed
+    SchedulerStopped,
+    /// A replayed [Cassette](crate::Cassette) contains no response to the request
+    UnmatchedRequest(RecordedRequest),
+    /// The base url uses plain http for a host that isn't a loopback address
+    InsecureBaseUrl(String),
+    /// The field can't be searched, see [Query::for_field](crate::Query::for_field)
+    UnsearchableField(EntryField),
+    /// An error occurred while writing csv
+    #[cfg(feature = "csv")]
+    CsvError(csv::Error),
+    /// An error occurred while parsing a config file
+    #[cfg(feature = "config")]
+    TomlError(toml::de::Error),
+    /// The config file is accessible by other users
+    #[cfg(feature = "config")]
+    InsecureConfig(PathBuf),
+    /// The requested profile doesn't exist in the config file
+    #[cfg(feature = "config")]
+    UnknownProfile(String),
+    /// The profile is missing the email or the api key
+    #[cfg(feature = "config")]
+    MissingCredentials,
+    /// An encrypted archive couldn't be decrypted, the key is wrong or the archive was modified
+    #[cfg(feature = "encryption")]
+    DecryptionFailed,
+    /// The version of an encrypted archive is not supported
+    #[cfg(feature = "encryption")]
+    UnsupportedArchiveVersion(u8),
 }
 
 impl Display for DehashedError {
@@ -32,12 +79,70 @@ impl Display for DehashedError {
                 write!(f, "An error occurred while parsing a response: {err}")
             }
             DehashedError::ParseAddrError(err) => write!(f, "Error while parsing ip addr: {err}"),
+            DehashedError::BudgetExceeded(limit) => {
+                write!(f, "The request would exceed the budget: {limit:?}")
+            }
+            DehashedError::IoError(err) => write!(f, "An io error occurred: {err}"),
+            DehashedError::JsonError(err) => write!(f, "Error while processing json: {err}"),
+            DehashedError::InvalidArchive => write!(f, "The archive header is missing or invalid"),
+            DehashedError::SchedulerStopped => write!(f, "The scheduler was stopped"),
+            DehashedError::UnmatchedRequest(request) => write!(
+                f,
+                "No recorded response to page {} of {} with size {}",
+                request.page, request.query, request.size
+            ),
+            DehashedError::InsecureBaseUrl(url) => write!(
+                f,
+                "Plain http is only allowed for loopback addresses, use https for {url}"
+            ),
+            DehashedError::UnsearchableField(field) => {
+                write!(f, "Dehashed doesn't support searching the field {field}")
+            }
+            #[cfg(feature = "csv")]
+            DehashedError::CsvError(err) => write!(f, "Error while writing csv: {err}"),
+            #[cfg(feature = "config")]
+            DehashedError::TomlError(err) => write!(f, "Invalid config file: {err}"),
+            #[cfg(feature = "config")]
+            DehashedError::InsecureConfig(path) => write!(
+                f,
+                "The config file {} must not be accessible by other users",
+                path.display()
+            ),
+            #[cfg(feature = "config")]
+            DehashedError::UnknownProfile(name) => write!(f, "Unknown profile {name}"),
+            #[cfg(feature = "config")]
+            DehashedError::MissingCredentials => write!(f, "The email or api key is missing"),
+            #[cfg(feature = "encryption")]
+            DehashedError::DecryptionFailed => {
+                write!(
+                    f,
+                    "Decryption failed, the key is wrong or the archive was modified"
+                )
+            }
+            #[cfg(feature = "encryption")]
+            DehashedError::UnsupportedArchiveVersion(version) => {
+                write!(f, "Unsupported archive version {version}")
+            }
         }
     }
 }
 
 impl std::error::Error for DehashedError {}
 
+impl DehashedError {
+    /// Whether the error affects all further requests, not just the failed one
+    pub(crate) fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            DehashedError::Unauthorized
+                | DehashedError::RateLimited
+                | DehashedError::BudgetExceeded(_)
+                | DehashedError::SchedulerStopped
+                | DehashedError::UnmatchedRequest(_)
+        )
+    }
+}
+
 impl From<reqwest::Error> for DehashedError {
     fn from(value: reqwest::Error) -> Self {
         Self::ReqwestError(value)
@@ -55,3 +160,29 @@ impl From<AddrParseError> for DehashedError {
         Self::ParseAddrError(value)
     }
 }
+
+impl From<std::io::Error> for DehashedError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IoError(value)
+    }
+}
+
+impl From<serde_json::Error> for DehashedError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::JsonError(value)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for DehashedError {
+    fn from(value: csv::Error) -> Self {
+        Self::CsvError(value)
+    }
+}
+
+#[cfg(feature = "config")]
+impl From<toml::de::Error> for DehashedError {
+    fn from(value: toml::de::Error) -> Self {
+        Self::TomlError(value)
+    }
+}
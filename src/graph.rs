@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{EntryField, SearchResult};
+use crate::error::DehashedError;
+use crate::multi::MultiSearchEntry;
+
+/// An identifier in an [IdentityGraph]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GraphNode {
+    /// The field the identifier was found in
+    pub field: EntryField,
+    /// The normalised value of the identifier
+    pub value: String,
+}
+
+/// A link between two identifiers that appeared in the same entries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GraphEdge {
+    /// The index of the first node
+    pub source: usize,
+    /// The index of the second node
+    pub target: usize,
+    /// The ids of the entries both identifiers appeared in
+    pub entry_ids: Vec<u64>,
+}
+
+/// A graph correlating the identifiers of the entries of a [SearchResult].
+///
+/// Every distinct identifier is a node, identifiers that appear in the same entry
+/// are linked by an edge.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct IdentityGraph {
+    /// All identifiers
+    pub nodes: Vec<GraphNode>,
+    /// All links between the identifiers
+    pub edges: Vec<GraphEdge>,
+}
+
+impl IdentityGraph {
+    /// The fields used as identifiers by [IdentityGraph::from_result]
+    pub const IDENTIFIER_FIELDS: [EntryField; 6] = [
+        EntryField::Email,
+        EntryField::Username,
+        EntryField::Phone,
+        EntryField::IpAddress,
+        EntryField::Social,
+        EntryField::CryptocurrencyAddress,
+    ];
+
+    /// Build a graph using the [IdentityGraph::IDENTIFIER_FIELDS]
+    pub fn from_result(result: &SearchResult) -> Self {
+        Self::from_result_with_fields(result, &Self::IDENTIFIER_FIELDS)
+    }
+
+    /// Build a graph using the provided fields as identifiers.
+    ///
+    /// Values are trimmed, emails and usernames are converted to lower case.
+    /// Comma-joined values are split, see [MultiSearchEntry].
+    pub fn from_result_with_fields(result: &SearchResult, fields: &[EntryField]) -> Self {
+        let mut graph = IdentityGraph::default();
+        let mut node_index: HashMap<GraphNode, usize> = HashMap::new();
+        let mut edge_index: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for entry in &result.entries {
+            let entry = MultiSearchEntry::from(entry.clone());
+
+            let mut nodes = vec![];
+            for field in fields {
+                for value in entry.values(*field) {
+                    let value = match field {
+                        EntryField::Email | EntryField::Username => value.trim().to_lowercase(),
+                        _ => value.trim().to_string(),
+                    };
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let node = GraphNode {
+                        field: *field,
+                        value,
+                    };
+                    let i = *node_index.entry(node.clone()).or_insert_with(|| {
+                        graph.nodes.push(node);
+                        graph.nodes.len() - 1
+                    });
+                    if !nodes.contains(&i) {
+                        nodes.push(i);
+                    }
+                }
+            }
+
+            for (i, source) in nodes.iter().enumerate() {
+                for target in &nodes[i + 1..] {
+                    let key = (*source.min(target), *source.max(target));
+                    let e = *edge_index.entry(key).or_insert_with(|| {
+                        graph.edges.push(GraphEdge {
+                            source: key.0,
+                            target: key.1,
+                            entry_ids: vec![],
+                        });
+                        graph.edges.len() - 1
+                    });
+                    graph.edges[e].entry_ids.push(entry.id);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Find the index of a node
+    pub fn find(&self, field: EntryField, value: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|x| x.field == field && x.value == value)
+    }
+
+    /// Cluster the nodes into connected components.
+    ///
+    /// Every component is a list of node indices, probably belonging to the same person.
+    /// The components are ordered by descending size.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut parents: Vec<usize> = (0..self.nodes.len()).collect();
+
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+
+        for edge in &self.edges {
+            let a = root(&mut parents, edge.source);
+            let b = root(&mut parents, edge.target);
+            if a != b {
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+
+        let mut components: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..self.nodes.len() {
+            components.entry(root(&mut parents, i)).or_default().push(i);
+        }
+        let mut components: Vec<_> = components.into_values().collect();
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+        components
+    }
+
+    /// Retrieve all nodes in the same component as the provided node, excluding itself
+    pub fn related(&self, field: EntryField, value: &str) -> Vec<&GraphNode> {
+        let Some(node) = self.find(field, value) else {
+            return vec![];
+        };
+        self.components()
+            .into_iter()
+            .find(|x| x.contains(&node))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|x| *x != node)
+            .map(|x| &self.nodes[x])
+            .collect()
+    }
+
+    /// Write the graph in the DOT format of Graphviz
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> Result<(), DehashedError> {
+        writeln!(writer, "graph identities {{")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                writer,
+                "  n{i} [label=\"{}\", field=\"{}\"];",
+                escape_dot(&node.value),
+                node.field
+            )?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                writer,
+                "  n{} -- n{} [weight={}];",
+                edge.source,
+                edge.target,
+                edge.entry_ids.len()
+            )?;
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the graph in the GraphML format
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> Result<(), DehashedError> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="field" for="node" attr.name="field" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="value" for="node" attr.name="value" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="entries" for="edge" attr.name="entries" attr.type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <graph id="identities" edgedefault="undirected">"#
+        )?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"    <node id="n{i}"><data key="field">{}</data><data key="value">{}</data></node>"#,
+                node.field,
+                escape_xml(&node.value)
+            )?;
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            let ids: Vec<String> = edge.entry_ids.iter().map(u64::to_string).collect();
+            writeln!(
+                writer,
+                r#"    <edge id="e{i}" source="n{}" target="n{}"><data key="entries">{}</data></edge>"#,
+                edge.source,
+                edge.target,
+                ids.join(",")
+            )?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Escape a value for a quoted DOT string
+fn escape_dot(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escape a value for XML
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
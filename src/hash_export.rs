@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::SearchResult;
+use crate::error::DehashedError;
+use crate::hashes::HashKind;
+use crate::secret::Redacted;
+
+/// The file format of a [HashExport]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum HashFormat {
+    /// One hash per line
+    Hashcat,
+    /// One `user:hash` per line, to be used with the `--username` option of hashcat
+    HashcatWithUser,
+    /// One `user:hash` per line, to be used with the `--format` option of john
+    John,
+}
+
+/// A single deduplicated hash of a [HashExport]
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExportedHash {
+    /// The hash
+    pub hash: String,
+    /// The users the hash belongs to, the email, username or id of the entries
+    pub users: Vec<String>,
+    /// The ids of the entries the hash was found in
+    pub entry_ids: Vec<u64>,
+}
+
+/// Prints the users and entry ids, replacing the hash with [REDACTED](crate::REDACTED)
+impl Debug for ExportedHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExportedHash")
+            .field("hash", &Redacted)
+            .field("users", &self.users)
+            .field("entry_ids", &self.entry_ids)
+            .finish()
+    }
+}
+
+/// The hashed passwords of a [SearchResult] grouped by their [HashKind]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HashExport {
+    /// The deduplicated hashes of every detected algorithm
+    pub groups: BTreeMap<HashKind, Vec<ExportedHash>>,
+    /// The ids of the entries whose hash algorithm couldn't be detected
+    pub unknown: Vec<u64>,
+}
+
+impl HashExport {
+    /// Collect and deduplicate the hashed passwords of a result.
+    ///
+    /// The algorithm is detected using [HashKind::detect], so hashes of an ambiguous shape
+    /// are assigned to their most likely algorithm.
+    /// Notably, upper case 32 character hex is always exported as [HashKind::Ntlm]
+    /// and lower case as [HashKind::Md5].
+    pub fn from_result(result: &SearchResult) -> Self {
+        let mut export = HashExport::default();
+        let mut index: BTreeMap<(HashKind, String), usize> = BTreeMap::new();
+
+        for entry in &result.entries {
+            let Some(hash) = entry.hashed_password.as_deref().map(str::trim) else {
+                continue;
+            };
+            let Some(kind) = HashKind::detect(hash) else {
+                export.unknown.push(entry.id);
+                continue;
+            };
+            let user = entry
+                .email
+                .clone()
+                .or_else(|| entry.username.clone())
+                .unwrap_or_else(|| entry.id.to_string())
+                .replace(':', "_");
+
+            let group = export.groups.entry(kind).or_default();
+            let i = *index.entry((kind, hash.to_string())).or_insert_with(|| {
+                group.push(ExportedHash {
+                    hash: hash.to_string(),
+                    users: vec![],
+                    entry_ids: vec![],
+                });
+                group.len() - 1
+            });
+            let exported = &mut group[i];
+            if !exported.users.contains(&user) {
+                exported.users.push(user);
+            }
+            exported.entry_ids.push(entry.id);
+        }
+
+        export
+    }
+
+    /// Write the hashes of a single algorithm in the provided format.
+    ///
+    /// Hashes that the tool can't parse are skipped, which are the [HashKind::Scrypt]
+    /// hashes in the `$7$` and `$scrypt$` formats for hashcat, as it only supports `SCRYPT:`.
+    pub fn write<W: Write>(
+        &self,
+        kind: HashKind,
+        format: HashFormat,
+        mut writer: W,
+    ) -> Result<(), DehashedError> {
+        for exported in self.groups.get(&kind).into_iter().flatten() {
+            if kind == HashKind::Scrypt
+                && format != HashFormat::John
+                && !exported.hash.starts_with("SCRYPT:")
+            {
+                continue;
+            }
+            // hashcat expects MySQL 4.1 hashes without the leading asterisk
+            let hash = match (kind, format) {
+                (HashKind::MySql41, HashFormat::Hashcat | HashFormat::HashcatWithUser) => {
+                    exported.hash.trim_start_matches('*')
+                }
+                _ => &exported.hash,
+            };
+            match format {
+                HashFormat::Hashcat => writeln!(writer, "{hash}")?,
+                HashFormat::HashcatWithUser | HashFormat::John => {
+                    for user in &exported.users {
+                        writeln!(writer, "{user}:{hash}")?;
+                    }
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write a tab separated mapping of every hash to the ids of its entries.
+    ///
+    /// Every line consists of the algorithm, the hash and the comma separated ids.
+    pub fn write_mapping<W: Write>(&self, mut writer: W) -> Result<(), DehashedError> {
+        for (kind, hashes) in &self.groups {
+            for hash in hashes {
+                let ids: Vec<String> = hash.entry_ids.iter().map(u64::to_string).collect();
+                writeln!(writer, "{kind}\t{}\t{}", hash.hash, ids.join(","))?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write one file per algorithm and a `mapping.tsv` into a directory.
+    ///
+    /// The files for hashcat are named by mode and algorithm, e.g. `1400-sha256.txt`,
+    /// algorithms hashcat doesn't support are skipped.
+    /// The files for john are named by its format, e.g. `raw-sha256.john`,
+    /// algorithms john doesn't support are skipped.
+    ///
+    /// Returns the paths of the written files.
+    pub fn write_to_dir(
+        &self,
+        dir: &Path,
+        format: HashFormat,
+    ) -> Result<Vec<PathBuf>, DehashedError> {
+        let mut paths = vec![];
+        for kind in self.groups.keys() {
+            let file_name = match format {
+                HashFormat::Hashcat | HashFormat::HashcatWithUser => match kind.hashcat_mode() {
+                    Some(mode) => format!("{mode}-{kind}.txt"),
+                    None => continue,
+                },
+                HashFormat::John => match kind.john_format() {
+                    Some(format) => format!("{format}.john"),
+                    None => continue,
+                },
+            };
+            let path = dir.join(file_name);
+            self.write(*kind, format, BufWriter::new(File::create(&path)?))?;
+            paths.push(path);
+        }
+
+        let path = dir.join("mapping.tsv");
+        self.write_mapping(BufWriter::new(File::create(&path)?))?;
+        paths.push(path);
+
+        Ok(paths)
+    }
+}
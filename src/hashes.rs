@@ -0,0 +1,286 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// The algorithm of a hashed password
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum HashKind {
+    /// Unsalted MD5
+    Md5,
+    /// Unsalted MD4
+    Md4,
+    /// NTLM, MD4 of the UTF-16LE encoded password
+    Ntlm,
+    /// Unsalted SHA-1
+    Sha1,
+    /// Unsalted SHA-224
+    Sha224,
+    /// Unsalted SHA-256
+    Sha256,
+    /// Unsalted SHA-384
+    Sha384,
+    /// Unsalted SHA-512
+    Sha512,
+    /// bcrypt (`$2a$`, `$2b$`, `$2x$`, `$2y$`)
+    Bcrypt,
+    /// Traditional DES based crypt
+    DesCrypt,
+    /// MD5 based crypt (`$1$`)
+    Md5Crypt,
+    /// SHA-1 based crypt (`$sha1$`)
+    Sha1Crypt,
+    /// SHA-256 based crypt (`$5$`)
+    Sha256Crypt,
+    /// SHA-512 based crypt (`$6$`)
+    Sha512Crypt,
+    /// yescrypt (`$y$`)
+    Yescrypt,
+    /// scrypt (`$7$`, `$scrypt$`, `SCRYPT:`)
+    Scrypt,
+    /// Argon2 (`$argon2i$`, `$argon2d$`, `$argon2id$`)
+    Argon2,
+    /// MySQL before 4.1
+    MySql323,
+    /// MySQL 4.1 and later (`*` followed by SHA-1)
+    MySql41,
+    /// phpass as used by WordPress and phpBB (`$P$`, `$H$`)
+    Phpass,
+    /// PBKDF2-SHA256 as used by Django (`pbkdf2_sha256$`)
+    DjangoPbkdf2Sha256,
+    /// Salted SHA-1 as used by old Django versions (`sha1$`)
+    DjangoSha1,
+    /// PBKDF2-SHA256 in the format of passlib (`$pbkdf2-sha256$`)
+    Pbkdf2Sha256,
+    /// Salted SHA-1 as used by LDAP (`{SSHA}`)
+    Ssha,
+}
+
+/// A possible algorithm of a hash, see [HashKind::identify]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HashCandidate {
+    /// The algorithm
+    pub kind: HashKind,
+    /// The confidence in the range of `0.0` to `1.0`.
+    ///
+    /// The confidences of all candidates of a hash add up to at most `1.0`.
+    pub confidence: f32,
+}
+
+/// Prefixes that identify an algorithm unambiguously
+const PREFIXES: [(&str, HashKind); 19] = [
+    ("$2a$", HashKind::Bcrypt),
+    ("$2b$", HashKind::Bcrypt),
+    ("$2x$", HashKind::Bcrypt),
+    ("$2y$", HashKind::Bcrypt),
+    ("$1$", HashKind::Md5Crypt),
+    ("$sha1$", HashKind::Sha1Crypt),
+    ("$5$", HashKind::Sha256Crypt),
+    ("$6$", HashKind::Sha512Crypt),
+    ("$y$", HashKind::Yescrypt),
+    ("$7$", HashKind::Scrypt),
+    ("$scrypt$", HashKind::Scrypt),
+    ("SCRYPT:", HashKind::Scrypt),
+    ("$argon2", HashKind::Argon2),
+    ("$P$", HashKind::Phpass),
+    ("$H$", HashKind::Phpass),
+    ("pbkdf2_sha256$", HashKind::DjangoPbkdf2Sha256),
+    ("sha1$", HashKind::DjangoSha1),
+    ("$pbkdf2-sha256$", HashKind::Pbkdf2Sha256),
+    ("{SSHA}", HashKind::Ssha),
+];
+
+impl HashKind {
+    /// Identify the possible algorithms of a hash by the shape of its value.
+    ///
+    /// The candidates are ordered by descending confidence.
+    /// Returns an empty list if the shape is unknown.
+    pub fn identify(hash: &str) -> Vec<HashCandidate> {
+        let hash = hash.trim();
+        let candidate = |kind, confidence| HashCandidate { kind, confidence };
+
+        if let Some((_, kind)) = PREFIXES.iter().find(|(p, _)| hash.starts_with(p)) {
+            return vec![candidate(*kind, 1.0)];
+        }
+        if hash.len() == 41 && hash.starts_with('*') && is_hex(&hash[1..]) {
+            return vec![candidate(HashKind::MySql41, 1.0)];
+        }
+
+        if is_hex(hash) {
+            let upper = hash.chars().all(|c| !c.is_ascii_lowercase());
+            return match hash.len() {
+                16 => vec![candidate(HashKind::MySql323, 0.9)],
+                // NTLM hashes are usually dumped in upper case, MD5 in lower case
+                32 if upper => vec![
+                    candidate(HashKind::Ntlm, 0.5),
+                    candidate(HashKind::Md5, 0.4),
+                    candidate(HashKind::Md4, 0.1),
+                ],
+                32 => vec![
+                    candidate(HashKind::Md5, 0.6),
+                    candidate(HashKind::Ntlm, 0.3),
+                    candidate(HashKind::Md4, 0.1),
+                ],
+                40 => vec![
+                    candidate(HashKind::Sha1, 0.9),
+                    candidate(HashKind::MySql41, 0.1),
+                ],
+                56 => vec![candidate(HashKind::Sha224, 0.9)],
+                64 => vec![candidate(HashKind::Sha256, 0.9)],
+                96 => vec![candidate(HashKind::Sha384, 0.9)],
+                128 => vec![candidate(HashKind::Sha512, 0.9)],
+                _ => vec![],
+            };
+        }
+
+        if hash.len() == 13
+            && hash
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '/')
+        {
+            // Could as well be a random plaintext of the same shape
+            return vec![candidate(HashKind::DesCrypt, 0.5)];
+        }
+
+        vec![]
+    }
+
+    /// Detect the most likely algorithm of a hash by the shape of its value.
+    ///
+    /// Returns [None] if the shape is unknown, see [HashKind::identify].
+    /// Ambiguous shapes always resolve to the same algorithm, e.g. upper case 32 character
+    /// hex is always detected as [HashKind::Ntlm], even though it may be MD5 or MD4.
+    /// Use [HashKind::identify] to retrieve all candidates.
+    pub fn detect(hash: &str) -> Option<HashKind> {
+        Self::identify(hash).first().map(|x| x.kind)
+    }
+
+    /// Whether the algorithm is fast and unsalted or weakly salted.
+    ///
+    /// Hashes of these algorithms can be cracked at very high rates,
+    /// so an exposure is nearly equivalent to an exposure of the plaintext.
+    pub fn is_fast(&self) -> bool {
+        matches!(
+            self,
+            HashKind::Md5
+                | HashKind::Md4
+                | HashKind::Ntlm
+                | HashKind::Sha1
+                | HashKind::Sha224
+                | HashKind::Sha256
+                | HashKind::Sha384
+                | HashKind::Sha512
+                | HashKind::DesCrypt
+                | HashKind::MySql323
+                | HashKind::MySql41
+                | HashKind::DjangoSha1
+                | HashKind::Ssha
+        )
+    }
+
+    /// The mode hashcat uses for this algorithm, [None] if hashcat doesn't support it.
+    ///
+    /// The mode of [HashKind::Scrypt] only applies to the `SCRYPT:` format.
+    pub fn hashcat_mode(&self) -> Option<u32> {
+        match self {
+            HashKind::Md5 => Some(0),
+            HashKind::Md4 => Some(900),
+            HashKind::Ntlm => Some(1000),
+            HashKind::Sha1 => Some(100),
+            HashKind::Sha224 => Some(1300),
+            HashKind::Sha256 => Some(1400),
+            HashKind::Sha384 => Some(10800),
+            HashKind::Sha512 => Some(1700),
+            HashKind::Bcrypt => Some(3200),
+            HashKind::DesCrypt => Some(1500),
+            HashKind::Md5Crypt => Some(500),
+            HashKind::Sha1Crypt => Some(15100),
+            HashKind::Sha256Crypt => Some(7400),
+            HashKind::Sha512Crypt => Some(1800),
+            HashKind::Yescrypt => None,
+            HashKind::Scrypt => Some(8900),
+            HashKind::Argon2 => None,
+            HashKind::MySql323 => Some(200),
+            HashKind::MySql41 => Some(300),
+            HashKind::Phpass => Some(400),
+            HashKind::DjangoPbkdf2Sha256 => Some(10000),
+            HashKind::DjangoSha1 => Some(124),
+            HashKind::Pbkdf2Sha256 => Some(20300),
+            HashKind::Ssha => Some(111),
+        }
+    }
+
+    /// The format john the ripper uses for this algorithm, [None] if john doesn't support it
+    pub fn john_format(&self) -> Option<&'static str> {
+        match self {
+            HashKind::Md5 => Some("raw-md5"),
+            HashKind::Md4 => Some("raw-md4"),
+            HashKind::Ntlm => Some("nt"),
+            HashKind::Sha1 => Some("raw-sha1"),
+            HashKind::Sha224 => Some("raw-sha224"),
+            HashKind::Sha256 => Some("raw-sha256"),
+            HashKind::Sha384 => Some("raw-sha384"),
+            HashKind::Sha512 => Some("raw-sha512"),
+            HashKind::Bcrypt => Some("bcrypt"),
+            HashKind::DesCrypt => Some("descrypt"),
+            HashKind::Md5Crypt => Some("md5crypt"),
+            HashKind::Sha1Crypt => Some("sha1crypt"),
+            HashKind::Sha256Crypt => Some("sha256crypt"),
+            HashKind::Sha512Crypt => Some("sha512crypt"),
+            HashKind::Yescrypt => Some("crypt"),
+            HashKind::Scrypt => Some("scrypt"),
+            HashKind::Argon2 => Some("argon2"),
+            HashKind::MySql323 => Some("mysql"),
+            HashKind::MySql41 => Some("mysql-sha1"),
+            HashKind::Phpass => Some("phpass"),
+            HashKind::DjangoPbkdf2Sha256 => Some("django"),
+            HashKind::DjangoSha1 => None,
+            HashKind::Pbkdf2Sha256 => Some("pbkdf2-hmac-sha256"),
+            HashKind::Ssha => Some("salted-sha1"),
+        }
+    }
+
+    /// A short name of the algorithm that is safe to use in file names
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashKind::Md5 => "md5",
+            HashKind::Md4 => "md4",
+            HashKind::Ntlm => "ntlm",
+            HashKind::Sha1 => "sha1",
+            HashKind::Sha224 => "sha224",
+            HashKind::Sha256 => "sha256",
+            HashKind::Sha384 => "sha384",
+            HashKind::Sha512 => "sha512",
+            HashKind::Bcrypt => "bcrypt",
+            HashKind::DesCrypt => "descrypt",
+            HashKind::Md5Crypt => "md5crypt",
+            HashKind::Sha1Crypt => "sha1crypt",
+            HashKind::Sha256Crypt => "sha256crypt",
+            HashKind::Sha512Crypt => "sha512crypt",
+            HashKind::Yescrypt => "yescrypt",
+            HashKind::Scrypt => "scrypt",
+            HashKind::Argon2 => "argon2",
+            HashKind::MySql323 => "mysql323",
+            HashKind::MySql41 => "mysql41",
+            HashKind::Phpass => "phpass",
+            HashKind::DjangoPbkdf2Sha256 => "django-pbkdf2-sha256",
+            HashKind::DjangoSha1 => "django-sha1",
+            HashKind::Pbkdf2Sha256 => "pbkdf2-sha256",
+            HashKind::Ssha => "ssha",
+        }
+    }
+}
+
+impl Display for HashKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Check whether a string is non-empty and only consists of hex digits
+fn is_hex(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit())
+}
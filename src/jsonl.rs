@@ -0,0 +1,148 @@
+use std::io::{BufRead, Lines, Write};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ConversionWarning, PageInfo, SearchEntry, SearchResult};
+use crate::error::DehashedError;
+
+/// The first record of a JSON Lines archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ArchiveHeader {
+    /// The query string that was searched for
+    pub query: String,
+    /// The point in time the archive was created
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub timestamp: SystemTime,
+    /// See [SearchResult::total]
+    pub total: usize,
+    /// See [SearchResult::balance]
+    pub balance: usize,
+    /// See [SearchResult::pages]
+    #[serde(default)]
+    pub pages: Vec<PageInfo>,
+    /// See [SearchResult::warnings]
+    #[serde(default)]
+    pub warnings: Vec<ConversionWarning>,
+}
+
+impl ArchiveHeader {
+    /// Create a header for the provided query and result, using the current time as timestamp
+    pub fn new(query: String, result: &SearchResult) -> Self {
+        Self {
+            query,
+            timestamp: SystemTime::now(),
+            total: result.total,
+            balance: result.balance,
+            pages: result.pages.clone(),
+            warnings: result.warnings.clone(),
+        }
+    }
+}
+
+/// A writer for JSON Lines archives.
+///
+/// The archive starts with an [ArchiveHeader] followed by one [SearchEntry] per line.
+pub struct JsonlWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    /// Create a new writer and write the header
+    pub fn new(mut writer: W, header: &ArchiveHeader) -> Result<Self, DehashedError> {
+        serde_json::to_writer(&mut writer, header)?;
+        writer.write_all(b"\n")?;
+        Ok(Self { writer })
+    }
+
+    /// Write a complete result as archive
+    pub fn write_archive(
+        writer: W,
+        query: String,
+        result: &SearchResult,
+    ) -> Result<W, DehashedError> {
+        let mut writer = Self::new(writer, &ArchiveHeader::new(query, result))?;
+        for entry in &result.entries {
+            writer.write_entry(entry)?;
+        }
+        writer.finish()
+    }
+
+    /// Write a single entry
+    pub fn write_entry(&mut self, entry: &SearchEntry) -> Result<(), DehashedError> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Flush the writer and return the underlying writer
+    pub fn finish(mut self) -> Result<W, DehashedError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// A reader for JSON Lines archives written by [JsonlWriter].
+///
+/// The header is read on construction, the entries are read lazily by iterating the reader.
+pub struct JsonlReader<R: BufRead> {
+    header: ArchiveHeader,
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> JsonlReader<R> {
+    /// Create a new reader and read the header.
+    ///
+    /// Fails with [DehashedError::InvalidArchive] if the header is missing or malformed.
+    pub fn new(reader: R) -> Result<Self, DehashedError> {
+        let mut lines = reader.lines();
+        let header = loop {
+            match lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    if !line.trim().is_empty() {
+                        break serde_json::from_str(&line)
+                            .map_err(|_| DehashedError::InvalidArchive)?;
+                    }
+                }
+                None => return Err(DehashedError::InvalidArchive),
+            }
+        };
+        Ok(Self { header, lines })
+    }
+
+    /// Retrieve the header of the archive
+    pub fn header(&self) -> &ArchiveHeader {
+        &self.header
+    }
+
+    /// Read all remaining entries and build a [SearchResult] from them
+    pub fn into_search_result(mut self) -> Result<SearchResult, DehashedError> {
+        let entries = self.by_ref().collect::<Result<_, _>>()?;
+        Ok(SearchResult {
+            entries,
+            balance: self.header.balance,
+            warnings: self.header.warnings,
+            total: self.header.total,
+            pages: self.header.pages,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlReader<R> {
+    type Item = Result<SearchEntry, DehashedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if !line.trim().is_empty() {
+                return Some(serde_json::from_str(&line).map_err(DehashedError::from));
+            }
+        }
+    }
+}
@@ -59,19 +59,93 @@
 //! ```
 //!
 //! If you need type definitions for utoipa, there available under the feature flag `utoipa`.
+//!
+//! Results can be exported as csv by enabling the feature flag `csv`.
+//!
+//! Known passwords can be checked locally against leaked hashes by enabling the
+//! feature flag `verify`.
+//!
+//! Results can be masked before sharing them by enabling the feature flag `redact`,
+//! see `RedactionPolicy`.
+//!
+//! Results can be stored encrypted by enabling the feature flag `encryption`,
+//! see `SearchResult::write_encrypted`.
+//!
+//! For testing without credentials, the feature flag `mock` provides a local `MockServer`
+//! imitating dehashed.
+//!
+//! Requests can be recorded to a [Cassette] and replayed later without spending credits,
+//! see [DehashedApi::start_recording] and [DehashedApi::replay].
+//!
+//! Credentials and settings can be loaded from named profiles of a toml config file
+//! by enabling the feature flag `config`, see `DehashedApi::from_profile`.
+//!
+//! A command line client is available as `dehashed` binary with the feature flag `cli`.
 
 #![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
 #![warn(missing_docs)]
 
+pub use analysis::*;
 pub use api::*;
+pub use batch::*;
+pub use budget::*;
+pub use cassette::*;
+#[cfg(feature = "config")]
+pub use config::*;
+pub use credentials::*;
+#[cfg(feature = "csv")]
+pub use csv_export::*;
+#[cfg(feature = "encryption")]
+pub use encryption::*;
 pub use error::DehashedError;
+pub use graph::*;
+pub use hash_export::*;
+pub use hashes::*;
+pub use jsonl::*;
+pub use merge::*;
+#[cfg(feature = "mock")]
+pub use mock::*;
+pub use multi::*;
+pub use pivot::*;
+#[cfg(feature = "redact")]
+pub use redact::*;
 #[cfg(feature = "tokio")]
 pub use scheduler::*;
+pub use secret::*;
+#[cfg(feature = "verify")]
+pub use verify::*;
 
+mod analysis;
 mod api;
+mod batch;
+mod budget;
+mod cassette;
+#[cfg(feature = "config")]
+mod config;
+mod credentials;
+#[cfg(feature = "verify")]
+mod crypt;
+#[cfg(feature = "csv")]
+mod csv_export;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod error;
+mod graph;
+mod hash_export;
+mod hashes;
+mod jsonl;
+mod merge;
+#[cfg(feature = "mock")]
+mod mock;
+mod multi;
+mod pivot;
+#[cfg(feature = "redact")]
+mod redact;
 pub(crate) mod res;
 #[cfg(feature = "tokio")]
 mod scheduler;
+mod secret;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "verify")]
+mod verify;
@@ -0,0 +1,100 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{EntryField, SearchEntry, SearchResult};
+
+/// The difference between two [SearchResult]s of the same query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SearchDiff {
+    /// Entries that are only present in the new result
+    pub added: Vec<SearchEntry>,
+    /// Entries that are only present in the old result
+    pub removed: Vec<SearchEntry>,
+}
+
+impl SearchEntry {
+    /// Calculate a hash over the content of all fields.
+    ///
+    /// The [SearchEntry::id] is not included, so the same record stored under
+    /// different ids results in the same hash.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for field in EntryField::ALL {
+            self.get(field).hash(&mut hasher);
+        }
+        let mut extra: Vec<_> = self.extra.iter().collect();
+        extra.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, value) in extra {
+            key.hash(&mut hasher);
+            value.to_string().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+impl SearchResult {
+    /// Merge another result into this one.
+    ///
+    /// The entries, warnings and pages are appended, the totals are added up and
+    /// the balance of `other` is kept, as it is expected to be the more recent one.
+    /// Entries are not deduplicated, use one of the `dedup` methods afterwards.
+    pub fn merge(&mut self, other: SearchResult) {
+        self.entries.extend(other.entries);
+        self.warnings.extend(other.warnings);
+        self.pages.extend(other.pages);
+        self.total += other.total;
+        self.balance = other.balance;
+    }
+
+    /// Remove all entries with an id that occurred before
+    pub fn dedup_by_id(&mut self) {
+        self.dedup_by_key(|x| x.id);
+    }
+
+    /// Remove all entries with a content that occurred before.
+    ///
+    /// See [SearchEntry::content_hash]
+    pub fn dedup_by_content(&mut self) {
+        self.dedup_by_key(SearchEntry::content_hash);
+    }
+
+    /// Remove all entries with a key that occurred before.
+    ///
+    /// The first occurrence of every key is kept, e.g. use
+    /// `result.dedup_by_key(|x| (x.email.clone(), x.password.clone()))`
+    /// to keep a single entry per combination of email and password.
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: Hash + Eq,
+        F: FnMut(&SearchEntry) -> K,
+    {
+        let mut seen = HashSet::new();
+        self.entries.retain(|x| seen.insert(key(x)));
+    }
+
+    /// Compare two results of the same query by the ids of their entries
+    pub fn diff(old: &SearchResult, new: &SearchResult) -> SearchDiff {
+        let old_ids: HashSet<u64> = old.entries.iter().map(|x| x.id).collect();
+        let new_ids: HashSet<u64> = new.entries.iter().map(|x| x.id).collect();
+
+        SearchDiff {
+            added: new
+                .entries
+                .iter()
+                .filter(|x| !old_ids.contains(&x.id))
+                .cloned()
+                .collect(),
+            removed: old
+                .entries
+                .iter()
+                .filter(|x| !new_ids.contains(&x.id))
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::error::DehashedError;
+use crate::DehashedApi;
+
+/// The email accepted by a [MockServer]
+pub const MOCK_EMAIL: &str = "mock@example.com";
+/// The api key accepted by a [MockServer]
+pub const MOCK_API_KEY: &str = "mock-api-key";
+
+/// The fields every entry of a dehashed response contains
+const REQUIRED_FIELDS: [&str; 11] = [
+    "id",
+    "email",
+    "username",
+    "password",
+    "hashed_password",
+    "ip_address",
+    "name",
+    "vin",
+    "address",
+    "phone",
+    "database_name",
+];
+
+/// The response of a [MockServer] to a query
+#[derive(Clone, Debug, Default)]
+pub struct MockFixture {
+    /// The entries in the format of dehashed.
+    ///
+    /// Missing fields are filled with empty strings, so `{"id": "1", "email": "a@b.c"}`
+    /// is a valid entry.
+    pub entries: Vec<Value>,
+    /// The total number of hits that is reported, defaults to the number of entries
+    pub total: Option<usize>,
+    /// Respond with this status code instead of the entries,
+    /// e.g. `302` for an invalid query, `400` if rate limited or `500`
+    pub status: Option<u16>,
+    /// The time to wait before responding
+    pub delay: Duration,
+}
+
+impl MockFixture {
+    /// Respond with entries
+    pub fn entries(entries: Vec<Value>) -> Self {
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    /// Respond with a status code
+    pub fn status(status: u16) -> Self {
+        Self {
+            status: Some(status),
+            ..Self::default()
+        }
+    }
+
+    /// Report a different total number of hits, e.g. to simulate a query exceeding the
+    /// [RESULT_WINDOW](crate::RESULT_WINDOW)
+    pub fn with_total(mut self, total: usize) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// Wait before responding
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// A request received by a [MockServer]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockRequest {
+    /// The query string
+    pub query: String,
+    /// The requested page
+    pub page: usize,
+    /// The requested page size
+    pub size: usize,
+    /// Whether the request contained the [MOCK_EMAIL] and [MOCK_API_KEY]
+    pub authorized: bool,
+}
+
+#[derive(Debug)]
+struct MockState {
+    fixtures: HashMap<String, MockFixture>,
+    balance: usize,
+    requests: Vec<MockRequest>,
+}
+
+/// An in-process http server imitating the search endpoint of dehashed.
+///
+/// Use [MockServer::api] to retrieve a [DehashedApi] that is connected to the server.
+/// Queries without a fixture return no entries.
+/// Every successful request decrements the balance by one.
+/// The server is stopped when dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on a random local port with a balance of 1,000 credits
+    pub fn start() -> Result<Self, DehashedError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            fixtures: HashMap::new(),
+            balance: 1_000,
+            requests: vec![],
+        }));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let state = state.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let state = state.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = handle(stream, &state) {
+                            warn!("Mock server failed to handle request: {err}");
+                        }
+                    });
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            state,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    /// The url of the search endpoint
+    pub fn url(&self) -> String {
+        format!("http://{}/search", self.addr)
+    }
+
+    /// Create a [DehashedApi] that is connected to this server and doesn't delay requests
+    pub fn api(&self) -> Result<DehashedApi, DehashedError> {
+        let mut api = DehashedApi::new(MOCK_EMAIL.to_string(), MOCK_API_KEY.to_string())?;
+        api.set_base_url(self.url())?;
+        api.set_request_delay(Duration::ZERO);
+        Ok(api)
+    }
+
+    /// Set the response to a query string, e.g. `email:"test@example.com"`
+    pub fn set_fixture(&self, query: &str, fixture: MockFixture) {
+        self.state
+            .lock()
+            .unwrap()
+            .fixtures
+            .insert(query.to_string(), fixture);
+    }
+
+    /// Set the balance of the account
+    pub fn set_balance(&self, balance: usize) {
+        self.state.lock().unwrap().balance = balance;
+    }
+
+    /// Retrieve the balance of the account
+    pub fn balance(&self) -> usize {
+        self.state.lock().unwrap().balance
+    }
+
+    /// Retrieve all requests received so far
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wake up the listener, so it notices the server was stopped
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Handle a single http request
+fn handle(mut stream: TcpStream, state: &Mutex<MockState>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let target = request_line.split(' ').nth(1).unwrap_or_default();
+    let (path, params) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/search" {
+        return respond(&mut stream, 404, &json!({ "message": "Not found" }));
+    }
+    let params: HashMap<String, String> = params
+        .split('&')
+        .filter_map(|x| x.split_once('='))
+        .map(|(k, v)| (decode(k), decode(v)))
+        .collect();
+    let request = MockRequest {
+        query: params.get("query").cloned().unwrap_or_default(),
+        page: params.get("page").and_then(|x| x.parse().ok()).unwrap_or(1),
+        size: params
+            .get("size")
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(10_000),
+        authorized: authorization.as_deref()
+            == Some(&format!(
+                "Basic {}",
+                base64(&format!("{MOCK_EMAIL}:{MOCK_API_KEY}"))
+            )),
+    };
+
+    let fixture = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        state
+            .fixtures
+            .get(&request.query)
+            .cloned()
+            .unwrap_or_default()
+    };
+    thread::sleep(fixture.delay);
+
+    if !request.authorized {
+        return respond(
+            &mut stream,
+            401,
+            &json!({ "message": "Invalid API credentials." }),
+        );
+    }
+    if let Some(status) = fixture.status {
+        return respond(
+            &mut stream,
+            status,
+            &json!({ "message": "Simulated error" }),
+        );
+    }
+
+    let start = (request.page.max(1) - 1).saturating_mul(request.size);
+    let entries: Vec<Value> = fixture
+        .entries
+        .iter()
+        .skip(start)
+        .take(request.size)
+        .map(complete_entry)
+        .collect();
+    let balance = {
+        let mut state = state.lock().unwrap();
+        state.balance = state.balance.saturating_sub(1);
+        state.balance
+    };
+    respond(
+        &mut stream,
+        200,
+        &json!({
+            "balance": balance,
+            "entries": if entries.is_empty() { Value::Null } else { Value::Array(entries) },
+            "success": true,
+            "took": "1ms",
+            "total": fixture.total.unwrap_or(fixture.entries.len()),
+        }),
+    )
+}
+
+/// Write a json response and close the connection
+fn respond(stream: &mut TcpStream, status: u16, body: &Value) -> std::io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Fill the missing fields of a fixture entry with empty strings
+fn complete_entry(entry: &Value) -> Value {
+    let mut entry = entry.clone();
+    if let Value::Object(map) = &mut entry {
+        if let Some(Value::Number(id)) = map.get("id") {
+            let id = id.to_string();
+            map.insert("id".to_string(), Value::String(id));
+        }
+        for field in REQUIRED_FIELDS {
+            map.entry(field)
+                .or_insert_with(|| Value::String(String::new()));
+        }
+    }
+    entry
+}
+
+/// Decode a component of a `application/x-www-form-urlencoded` query string
+fn decode(value: &str) -> String {
+    let mut bytes = vec![];
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = input.by_ref().take(2).collect();
+                match std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|x| u8::from_str_radix(x, 16).ok())
+                {
+                    Some(x) => bytes.push(x),
+                    None => {
+                        bytes.push(b'%');
+                        bytes.extend(hex);
+                    }
+                }
+            }
+            x => bytes.push(x),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Encode a value as base64 with padding
+fn base64(value: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in value.as_bytes().chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+use serde::de::{SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::api::{ConversionWarning, EntryField, PageInfo, SearchEntry};
+use crate::res::Entry;
+use crate::secret::{Redacted, RedactedValues};
+
+/// A variant of [SearchEntry] that is able to hold multiple values per field.
+///
+/// Newer responses of dehashed contain arrays for fields like `email`, `username` or `password`.
+/// Use [DehashedApi::search_multi](crate::DehashedApi::search_multi) to retrieve all of them.
+/// When deserializing, each field accepts a single value, an array of values or `null`.
+/// Empty values are dropped.
+/// Single values of `email`, `ip_address` and `phone` are split at commas,
+/// as older records contain comma-joined values for these fields.
+///
+/// Like [SearchEntry], the [Debug](std::fmt::Debug) implementation redacts the passwords.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MultiSearchEntry {
+    /// ID of the entry
+    #[serde(deserialize_with = "deserialize_id")]
+    pub id: u64,
+    /// All email addresses of the entry
+    #[serde(default, deserialize_with = "comma_joined")]
+    pub email: Vec<String>,
+    /// All usernames of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub username: Vec<String>,
+    /// All passwords of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub password: Vec<String>,
+    /// All hashed passwords of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub hashed_password: Vec<String>,
+    /// All ip addresses of the entry
+    #[serde(default, deserialize_with = "comma_joined")]
+    pub ip_address: Vec<String>,
+    /// All names of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub name: Vec<String>,
+    /// All vins of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub vin: Vec<String>,
+    /// All addresses of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub address: Vec<String>,
+    /// All phones of the entry
+    #[serde(default, deserialize_with = "comma_joined")]
+    pub phone: Vec<String>,
+    /// All database names of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub database_name: Vec<String>,
+    /// All types of the hashed passwords of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub hash_type: Vec<String>,
+    /// All urls of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub url: Vec<String>,
+    /// All social media handles of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub social: Vec<String>,
+    /// All companies of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub company: Vec<String>,
+    /// All dates of birth of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub dob: Vec<String>,
+    /// All cryptocurrency addresses of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub cryptocurrency_address: Vec<String>,
+    /// All license plates of the entry
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub license_plate: Vec<String>,
+    /// All fields of the result that are not known to this crate
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Prints all fields, replacing the values of secret fields and unknown fields
+/// with [REDACTED](crate::REDACTED)
+impl Debug for MultiSearchEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("MultiSearchEntry");
+        s.field("id", &self.id);
+        for field in EntryField::ALL {
+            let values = self.values(field);
+            if field.is_secret() {
+                s.field(field.name(), &vec![Redacted; values.len()]);
+            } else {
+                s.field(field.name(), &values);
+            }
+        }
+        s.field("extra", &RedactedValues(&self.extra)).finish()
+    }
+}
+
+/// The result of [DehashedApi::search_multi](crate::DehashedApi::search_multi)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MultiSearchResult {
+    /// A list of results
+    pub entries: Vec<MultiSearchEntry>,
+    /// The remaining balance
+    pub balance: usize,
+    /// Warnings that occurred while converting the entries in
+    /// [ConversionMode::Lenient](crate::ConversionMode::Lenient)
+    #[serde(default)]
+    pub warnings: Vec<ConversionWarning>,
+    /// The total number of hits dehashed reported for the query
+    #[serde(default)]
+    pub total: usize,
+    /// Information about every page that was fetched
+    #[serde(default)]
+    pub pages: Vec<PageInfo>,
+}
+
+/// A single value of a [MultiSearchEntry]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EntryValue {
+    /// ID of the entry the value originates from
+    pub id: u64,
+    /// The field the value was found in
+    pub field: EntryField,
+    /// The value
+    pub value: String,
+}
+
+/// Prints the value as [REDACTED](crate::REDACTED) if the field is secret
+impl Debug for EntryValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("EntryValue");
+        s.field("id", &self.id).field("field", &self.field);
+        if self.field.is_secret() {
+            s.field("value", &Redacted);
+        } else {
+            s.field("value", &self.value);
+        }
+        s.finish()
+    }
+}
+
+impl MultiSearchEntry {
+    /// Retrieve all values of a field
+    pub fn values(&self, field: EntryField) -> &[String] {
+        match field {
+            EntryField::Email => &self.email,
+            EntryField::Username => &self.username,
+            EntryField::Password => &self.password,
+            EntryField::HashedPassword => &self.hashed_password,
+            EntryField::IpAddress => &self.ip_address,
+            EntryField::Name => &self.name,
+            EntryField::Vin => &self.vin,
+            EntryField::Address => &self.address,
+            EntryField::Phone => &self.phone,
+            EntryField::DatabaseName => &self.database_name,
+            EntryField::HashType => &self.hash_type,
+            EntryField::Url => &self.url,
+            EntryField::Social => &self.social,
+            EntryField::Company => &self.company,
+            EntryField::Dob => &self.dob,
+            EntryField::CryptocurrencyAddress => &self.cryptocurrency_address,
+            EntryField::LicensePlate => &self.license_plate,
+        }
+    }
+
+    fn values_mut(&mut self, field: EntryField) -> &mut Vec<String> {
+        match field {
+            EntryField::Email => &mut self.email,
+            EntryField::Username => &mut self.username,
+            EntryField::Password => &mut self.password,
+            EntryField::HashedPassword => &mut self.hashed_password,
+            EntryField::IpAddress => &mut self.ip_address,
+            EntryField::Name => &mut self.name,
+            EntryField::Vin => &mut self.vin,
+            EntryField::Address => &mut self.address,
+            EntryField::Phone => &mut self.phone,
+            EntryField::DatabaseName => &mut self.database_name,
+            EntryField::HashType => &mut self.hash_type,
+            EntryField::Url => &mut self.url,
+            EntryField::Social => &mut self.social,
+            EntryField::Company => &mut self.company,
+            EntryField::Dob => &mut self.dob,
+            EntryField::CryptocurrencyAddress => &mut self.cryptocurrency_address,
+            EntryField::LicensePlate => &mut self.license_plate,
+        }
+    }
+
+    /// Flatten the entry into one [EntryValue] per value of every field.
+    ///
+    /// The values are ordered by the fields in [EntryField::ALL].
+    pub fn rows(&self) -> Vec<EntryValue> {
+        EntryField::ALL
+            .into_iter()
+            .flat_map(|field| {
+                self.values(field).iter().map(move |value| EntryValue {
+                    id: self.id,
+                    field,
+                    value: value.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Flatten the values of a single field into one [EntryValue] per value
+    pub fn rows_of(&self, field: EntryField) -> Vec<EntryValue> {
+        self.values(field)
+            .iter()
+            .map(|value| EntryValue {
+                id: self.id,
+                field,
+                value: value.clone(),
+            })
+            .collect()
+    }
+}
+
+/// The fields which dehashed joins with a comma if a record contains multiple values
+const COMMA_JOINED: [EntryField; 3] = [
+    EntryField::Email,
+    EntryField::IpAddress,
+    EntryField::Phone,
+];
+
+impl MultiSearchEntry {
+    /// Convert an [Entry] with an already parsed id, keeping all of its values
+    pub(crate) fn from_entry(value: Entry, id: u64) -> Self {
+        let mut entry = MultiSearchEntry {
+            id,
+            email: value.email,
+            username: value.username,
+            password: value.password,
+            hashed_password: value.hashed_password,
+            ip_address: value.ip_address,
+            name: value.name,
+            vin: value.vin,
+            address: value.address,
+            phone: value.phone,
+            database_name: value.database_name,
+            hash_type: value.hash_type,
+            url: value.url,
+            social: value.social,
+            company: value.company,
+            dob: value.dob,
+            cryptocurrency_address: value.cryptocurrency_address,
+            license_plate: value.license_plate,
+            extra: value.extra,
+        };
+        for field in COMMA_JOINED {
+            let values = entry.values_mut(field);
+            *values = values.iter().flat_map(|x| split_comma_joined(x)).collect();
+        }
+        entry
+    }
+}
+
+impl From<SearchEntry> for MultiSearchEntry {
+    /// Convert a [SearchEntry] into a [MultiSearchEntry].
+    ///
+    /// The values of [EntryField::Email], [EntryField::IpAddress] and [EntryField::Phone]
+    /// are split at commas, as older records contain comma-joined values for these fields.
+    fn from(value: SearchEntry) -> Self {
+        let mut entry = MultiSearchEntry {
+            id: value.id,
+            ..Default::default()
+        };
+        for field in EntryField::ALL {
+            let Some(v) = value.get(field) else {
+                continue;
+            };
+            let values = entry.values_mut(field);
+            if COMMA_JOINED.contains(&field) {
+                values.extend(split_comma_joined(&v));
+            } else {
+                values.push(v.into_owned());
+            }
+        }
+        entry.extra = value.extra;
+        entry
+    }
+}
+
+/// Deserialize an id that is either a number or a string containing a number
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        String(String),
+    }
+
+    match Id::deserialize(deserializer)? {
+        Id::Number(x) => Ok(x),
+        Id::String(x) => x.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Split a comma-joined value into its non-empty parts
+fn split_comma_joined(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+}
+
+/// Deserialize a single value, an array of values or `null` into a list of values
+pub(crate) fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    deserializer.deserialize_any(OneOrMany { split: false })
+}
+
+/// Like [one_or_many], but a single string is split at commas
+fn comma_joined<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    deserializer.deserialize_any(OneOrMany { split: true })
+}
+
+/// Visitor of [one_or_many] and [comma_joined]
+struct OneOrMany {
+    /// Whether a single string is split at commas
+    split: bool,
+}
+
+impl<'de> Visitor<'de> for OneOrMany {
+    type Value = Vec<String>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a string, an array of strings or null")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(if self.split {
+            split_comma_joined(v).collect()
+        } else if v.is_empty() {
+            vec![]
+        } else {
+            vec![v.to_string()]
+        })
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(vec![v.to_string()])
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(vec![v.to_string()])
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(vec![])
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(vec![])
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element::<Option<String>>()? {
+            if let Some(value) = value.filter(|x| !x.is_empty()) {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+}
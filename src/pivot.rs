@@ -0,0 +1,251 @@
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "tokio")]
+use tokio::time::sleep;
+
+use crate::api::{EntryField, Query, SearchResult, SearchType};
+use crate::credentials::is_junk;
+use crate::error::DehashedError;
+use crate::multi::MultiSearchEntry;
+use crate::DehashedApi;
+#[cfg(feature = "tokio")]
+use crate::Scheduler;
+
+/// Options for a pivot search
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PivotOptions {
+    /// The maximum number of hops from the seed query, `0` only searches the seed
+    pub max_depth: usize,
+    /// The maximum number of credits to spend, one credit is spent per fetched page.
+    ///
+    /// A query is only executed if the credits it may cost at most still fit into the limit.
+    /// This is 3 credits per query, as up to 3 pages are fetched, or more if
+    /// [QuerySplitting](crate::QuerySplitting) is configured.
+    pub max_credits: usize,
+    /// The fields whose values are searched in the next hop
+    pub fields: Vec<EntryField>,
+}
+
+impl Default for PivotOptions {
+    /// Follow emails, usernames, phones and ip addresses for two hops with up to 50 credits
+    fn default() -> Self {
+        Self {
+            max_depth: 2,
+            max_credits: 50,
+            fields: vec![
+                EntryField::Email,
+                EntryField::Username,
+                EntryField::Phone,
+                EntryField::IpAddress,
+            ],
+        }
+    }
+}
+
+/// The entry value that led to a follow-up query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PivotOrigin {
+    /// The index of the [PivotStep] whose result contained the entry
+    pub step: usize,
+    /// ID of the entry
+    pub entry_id: u64,
+    /// The field of the entry
+    pub field: EntryField,
+    /// The value of the field
+    pub value: String,
+}
+
+/// A single query executed during a pivot search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PivotStep {
+    /// The query
+    pub query: Query,
+    /// The number of hops from the seed query
+    pub depth: usize,
+    /// The value the query originates from, [None] for the seed query
+    pub origin: Option<PivotOrigin>,
+    /// The ids of the entries found by this query
+    pub entry_ids: Vec<u64>,
+    /// The error that occurred while executing the query
+    pub error: Option<String>,
+}
+
+/// The result of a pivot search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PivotResult {
+    /// All executed queries in the order of execution
+    pub steps: Vec<PivotStep>,
+    /// The combined results of all queries, deduplicated by id
+    pub result: SearchResult,
+    /// The number of credits spent
+    pub credits: usize,
+    /// Whether queries were skipped because [PivotOptions::max_credits] was reached
+    /// or the search was aborted
+    pub truncated: bool,
+}
+
+impl DehashedApi {
+    /// Expand a seed query breadth-first into follow-up queries.
+    ///
+    /// The values of [PivotOptions::fields] of every found entry are searched in the next hop,
+    /// every value is only searched once.
+    /// Failing follow-up queries are recorded in [PivotStep::error], the search is only aborted
+    /// if the credentials are invalid, the account got rate limited or the budget is exceeded.
+    /// The queries are delayed by [DehashedApi::request_delay].
+    pub async fn pivot(
+        &self,
+        seed: Query,
+        options: &PivotOptions,
+    ) -> Result<PivotResult, DehashedError> {
+        pivot_with(
+            seed,
+            options,
+            self.max_search_cost(),
+            self.effective_delay(),
+            |query| self.search(query),
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Scheduler {
+    /// Expand a seed query breadth-first into follow-up queries.
+    ///
+    /// See [DehashedApi::pivot], the queries are delayed by the scheduler.
+    pub async fn pivot(
+        &self,
+        seed: Query,
+        options: &PivotOptions,
+    ) -> Result<PivotResult, DehashedError> {
+        pivot_with(
+            seed,
+            options,
+            self.api.max_search_cost(),
+            Duration::ZERO,
+            |query| self.search(query),
+        )
+        .await
+    }
+}
+
+/// Run a pivot search with a custom search function.
+///
+/// `max_cost` is the maximum number of credits a single search may cost and `delay` is
+/// waited between two searches.
+pub(crate) async fn pivot_with<F, Fut>(
+    seed: Query,
+    options: &PivotOptions,
+    max_cost: usize,
+    #[cfg_attr(not(feature = "tokio"), allow(unused_variables))] delay: Duration,
+    mut search: F,
+) -> Result<PivotResult, DehashedError>
+where
+    F: FnMut(Query) -> Fut,
+    Fut: Future<Output = Result<SearchResult, DehashedError>>,
+{
+    let mut visited = HashSet::from([seed.to_string().to_lowercase()]);
+    let mut queue = VecDeque::from([(seed, 0, None)]);
+    let mut pivot = PivotResult {
+        steps: vec![],
+        result: SearchResult {
+            entries: vec![],
+            balance: 0,
+            warnings: vec![],
+            total: 0,
+            pages: vec![],
+        },
+        credits: 0,
+        truncated: false,
+    };
+
+    while let Some((query, depth, origin)) = queue.pop_front() {
+        if pivot.credits.saturating_add(max_cost) > options.max_credits {
+            pivot.truncated = true;
+            break;
+        }
+        #[cfg(feature = "tokio")]
+        if !pivot.steps.is_empty() {
+            sleep(delay).await;
+        }
+
+        debug!("Pivot query at depth {depth}: {query}");
+        let result = match search(query.clone()).await {
+            Ok(result) => result,
+            Err(err) if pivot.steps.is_empty() => return Err(err),
+            Err(err) => {
+                let fatal = err.is_fatal();
+                warn!("Pivot query {query} failed: {err}");
+                pivot.steps.push(PivotStep {
+                    query,
+                    depth,
+                    origin,
+                    entry_ids: vec![],
+                    error: Some(err.to_string()),
+                });
+                if fatal {
+                    pivot.truncated = true;
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let step = pivot.steps.len();
+        pivot.credits += result.pages.len();
+        if depth < options.max_depth {
+            for entry in &result.entries {
+                let entry = MultiSearchEntry::from(entry.clone());
+                for field in &options.fields {
+                    for value in entry.values(*field) {
+                        let value = value.trim();
+                        if is_junk(value) {
+                            continue;
+                        }
+                        let Some(next) =
+                            Query::for_field(*field, SearchType::Exact(value.to_string()))
+                        else {
+                            continue;
+                        };
+                        if visited.insert(next.to_string().to_lowercase()) {
+                            queue.push_back((
+                                next,
+                                depth + 1,
+                                Some(PivotOrigin {
+                                    step,
+                                    entry_id: entry.id,
+                                    field: *field,
+                                    value: value.to_string(),
+                                }),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        pivot.steps.push(PivotStep {
+            query,
+            depth,
+            origin,
+            entry_ids: result.entries.iter().map(|x| x.id).collect(),
+            error: None,
+        });
+        pivot.result.merge(result);
+    }
+
+    pivot.result.dedup_by_id();
+    Ok(pivot)
+}
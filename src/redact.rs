@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Write};
+
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::api::{EntryField, EntryIpAddr, SearchEntry, SearchResult};
+
+/// How the values of a field are redacted
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Redaction {
+    /// Keep the value as is
+    #[default]
+    Keep,
+    /// Only keep the first and the last character, e.g. `h*****2`.
+    ///
+    /// For emails, the domain is kept as well, e.g. `a***e@example.com`.
+    Mask,
+    /// Replace the value by the hex encoded HMAC-SHA256 of the value.
+    ///
+    /// Equal values result in equal hashes, so the values stay correlatable,
+    /// e.g. to analyse password reuse, without revealing them to anyone who doesn't know
+    /// the key.
+    /// Emails and usernames are converted to lower case before hashing.
+    Hmac,
+    /// Remove the value
+    Drop,
+}
+
+/// The [Redaction]s that are applied to the fields of a [SearchResult]
+#[derive(Clone)]
+pub struct RedactionPolicy {
+    default: Redaction,
+    fields: HashMap<EntryField, Redaction>,
+    hmac_key: Zeroizing<Vec<u8>>,
+}
+
+impl Debug for RedactionPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedactionPolicy")
+            .field("default", &self.default)
+            .field("fields", &self.fields)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedactionPolicy {
+    /// Create a new policy that keeps all fields.
+    ///
+    /// The `hmac_key` is used for [Redaction::Hmac], use a random key with at least 32 bytes
+    /// and keep it secret, otherwise the hashes can be reversed by brute force.
+    pub fn new(hmac_key: &[u8]) -> Self {
+        Self {
+            default: Redaction::Keep,
+            fields: HashMap::new(),
+            hmac_key: Zeroizing::new(hmac_key.to_vec()),
+        }
+    }
+
+    /// A policy for sharing results with third parties.
+    ///
+    /// Passwords are replaced by their HMAC to keep reuse analysis possible,
+    /// hashes, emails, usernames and phone numbers are masked and all other personal data
+    /// is dropped. The database name, hash type and id are kept.
+    pub fn shareable(hmac_key: &[u8]) -> Self {
+        Self::new(hmac_key)
+            .with_default(Redaction::Drop)
+            .with_field(EntryField::Password, Redaction::Hmac)
+            .with_field(EntryField::HashedPassword, Redaction::Mask)
+            .with_field(EntryField::Email, Redaction::Mask)
+            .with_field(EntryField::Username, Redaction::Mask)
+            .with_field(EntryField::Phone, Redaction::Mask)
+            .with_field(EntryField::DatabaseName, Redaction::Keep)
+            .with_field(EntryField::HashType, Redaction::Keep)
+    }
+
+    /// Set the [Redaction] of all fields without an explicit one.
+    ///
+    /// Unknown fields of an entry, see [SearchEntry::extra], are dropped
+    /// unless the default is [Redaction::Keep].
+    pub fn with_default(mut self, redaction: Redaction) -> Self {
+        self.default = redaction;
+        self
+    }
+
+    /// Set the [Redaction] of a field
+    pub fn with_field(mut self, field: EntryField, redaction: Redaction) -> Self {
+        self.fields.insert(field, redaction);
+        self
+    }
+
+    /// Retrieve the [Redaction] that is applied to a field
+    pub fn redaction(&self, field: EntryField) -> Redaction {
+        self.fields.get(&field).copied().unwrap_or(self.default)
+    }
+
+    /// Redact a single value of a field, [None] if the value is dropped
+    pub fn redact_value(&self, field: EntryField, value: &str) -> Option<String> {
+        match self.redaction(field) {
+            Redaction::Keep => Some(value.to_string()),
+            Redaction::Mask => Some(match (field, value.rsplit_once('@')) {
+                (EntryField::Email, Some((local, domain))) => format!("{}@{domain}", mask(local)),
+                _ => mask(value),
+            }),
+            Redaction::Hmac => {
+                let value = match field {
+                    EntryField::Email | EntryField::Username => value.trim().to_lowercase(),
+                    _ => value.trim().to_string(),
+                };
+                let mut mac = Hmac::<Sha256>::new_from_slice(&self.hmac_key)
+                    .expect("HMAC can take keys of any size");
+                mac.update(value.as_bytes());
+                let mut hex = String::with_capacity(64);
+                for byte in mac.finalize().into_bytes() {
+                    let _ = write!(hex, "{byte:02x}");
+                }
+                Some(hex)
+            }
+            Redaction::Drop => None,
+        }
+    }
+
+    /// Apply the policy to an entry
+    pub fn redact_entry(&self, entry: &SearchEntry) -> SearchEntry {
+        let mut entry = entry.clone();
+        for field in EntryField::ALL {
+            if self.redaction(field) == Redaction::Keep {
+                continue;
+            }
+            if field == EntryField::IpAddress {
+                entry.ip_address = entry
+                    .ip_address
+                    .take()
+                    .and_then(|x| self.redact_value(field, &x.to_string()))
+                    .map(EntryIpAddr::Raw);
+                continue;
+            }
+            let value = value_mut(&mut entry, field);
+            *value = value.take().and_then(|x| self.redact_value(field, &x));
+        }
+        if self.default != Redaction::Keep {
+            entry.extra.clear();
+        }
+        entry
+    }
+
+    /// Apply the policy to all entries of a result.
+    ///
+    /// As conversion warnings may contain the original values, only the warnings of fields
+    /// that are kept are retained.
+    pub fn redact_result(&self, result: &SearchResult) -> SearchResult {
+        SearchResult {
+            entries: result
+                .entries
+                .iter()
+                .map(|x| self.redact_entry(x))
+                .collect(),
+            balance: result.balance,
+            warnings: result
+                .warnings
+                .iter()
+                .filter(|x| {
+                    x.field
+                        .is_some_and(|x| self.redaction(x) == Redaction::Keep)
+                })
+                .cloned()
+                .collect(),
+            total: result.total,
+            pages: result.pages.clone(),
+        }
+    }
+}
+
+/// Keep the first and the last character of a value
+fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    match chars.len() {
+        0 => String::new(),
+        1..=2 => "*".repeat(chars.len()),
+        n => format!("{}{}{}", chars[0], "*".repeat(n - 2), chars[n - 1]),
+    }
+}
+
+/// Retrieve the value of a string field of an entry
+fn value_mut(entry: &mut SearchEntry, field: EntryField) -> &mut Option<String> {
+    match field {
+        EntryField::Email => &mut entry.email,
+        EntryField::Username => &mut entry.username,
+        EntryField::Password => &mut entry.password,
+        EntryField::HashedPassword => &mut entry.hashed_password,
+        EntryField::Name => &mut entry.name,
+        EntryField::Vin => &mut entry.vin,
+        EntryField::Address => &mut entry.address,
+        EntryField::Phone => &mut entry.phone,
+        EntryField::DatabaseName => &mut entry.database_name,
+        EntryField::HashType => &mut entry.hash_type,
+        EntryField::Url => &mut entry.url,
+        EntryField::Social => &mut entry.social,
+        EntryField::Company => &mut entry.company,
+        EntryField::Dob => &mut entry.dob,
+        EntryField::CryptocurrencyAddress => &mut entry.cryptocurrency_address,
+        EntryField::LicensePlate => &mut entry.license_plate,
+        EntryField::IpAddress => unreachable!("The ip address is not a string field"),
+    }
+}
@@ -1,18 +1,59 @@
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::multi::one_or_many;
+
+/// An entry as returned by dehashed.
+///
+/// Older responses contain a single string per field, newer ones an array of strings,
+/// so every field accepts both shapes.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Entry {
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub(crate) id: String,
-    pub(crate) email: String,
-    pub(crate) username: String,
-    pub(crate) password: String,
-    pub(crate) hashed_password: String,
-    pub(crate) ip_address: String,
-    pub(crate) name: String,
-    pub(crate) vin: String,
-    pub(crate) address: String,
-    pub(crate) phone: String,
-    pub(crate) database_name: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) email: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) username: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) password: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) hashed_password: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) ip_address: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) name: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) vin: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) address: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) phone: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) database_name: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) hash_type: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) url: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) social: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) company: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) dob: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) cryptocurrency_address: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub(crate) license_plate: Vec<String>,
+    /// All fields that are not known (yet)
+    #[serde(flatten)]
+    pub(crate) extra: HashMap<String, serde_json::Value>,
+}
+
+/// Deserialize a missing or null string as empty string, which is treated as missing value
+fn null_as_empty<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -1,5 +1,4 @@
 use std::sync::Arc;
-use std::time::Duration;
 
 use log::warn;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -8,7 +7,7 @@ use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
 use crate::api::SearchResult;
-use crate::{DehashedApi, DehashedError, Query};
+use crate::{Budget, DehashedApi, DehashedError, Query};
 
 /// A search request for the [Scheduler].
 #[derive(Debug)]
@@ -35,6 +34,7 @@ impl ScheduledRequest {
 pub struct Scheduler {
     handle: Arc<JoinHandle<()>>,
     tx: Sender<ScheduledRequest>,
+    pub(crate) api: DehashedApi,
 }
 
 impl Scheduler {
@@ -49,15 +49,44 @@ impl Scheduler {
                 if req.ret.send(res).is_err() {
                     warn!("Couldn't send result back through channel");
                 }
-                sleep(Duration::from_millis(200)).await;
+                sleep(task_api.effective_delay()).await;
             }
         });
         Self {
             tx,
             handle: Arc::new(handle),
+            api: api.clone(),
         }
     }
 
+    /// Schedule a query and wait for its result.
+    ///
+    /// This is a shorthand for sending a [ScheduledRequest] through a sender retrieved by
+    /// [Scheduler::retrieve_sender].
+    pub async fn search(&self, query: Query) -> Result<SearchResult, DehashedError> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(ScheduledRequest::new(query, tx))
+            .await
+            .map_err(|_| DehashedError::SchedulerStopped)?;
+        rx.await.map_err(|_| DehashedError::SchedulerStopped)?
+    }
+
+    /// Set the [Budget] that limits the spending of credits.
+    ///
+    /// Scheduled requests that would cross a limit fail with
+    /// [DehashedError::BudgetExceeded].
+    pub fn set_budget(&self, budget: Budget) {
+        self.api.set_budget(budget);
+    }
+
+    /// Retrieve the remaining balance of the account.
+    ///
+    /// See [DehashedApi::remaining_balance]
+    pub fn remaining_balance(&self) -> Option<usize> {
+        self.api.remaining_balance()
+    }
+
     /// Retrieve a [Sender] to allow pushing tasks to the scheduler.
     ///
     /// To use multiple senders, you can clone the one you've received or
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+use serde::{Deserialize, Deserializer};
+use zeroize::Zeroize;
+
+/// The placeholder that is printed instead of a secret
+pub const REDACTED: &str = "<redacted>";
+
+/// A string that is redacted in [Debug] output and zeroed in memory when dropped
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a string
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// Retrieve the secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{REDACTED}")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Debug helper that prints [REDACTED]
+#[derive(Copy, Clone)]
+pub(crate) struct Redacted;
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{REDACTED}")
+    }
+}
+
+/// Debug helper that prints the keys of a map, replacing every value with [REDACTED]
+pub(crate) struct RedactedValues<'a, V>(pub(crate) &'a HashMap<String, V>);
+
+impl<V> Debug for RedactedValues<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|k| (k, Redacted)))
+            .finish()
+    }
+}
@@ -1,12 +1,43 @@
 use std::env;
+use std::time::{Duration, SystemTime};
 
 #[cfg(feature = "tokio")]
 use tokio::sync::oneshot;
 
-use crate::api::{Query, SearchType};
-use crate::DehashedApi;
+use crate::analysis::PasswordReport;
+#[cfg(feature = "mock")]
+use crate::api::QuerySplitting;
+use crate::api::{
+    ConversionWarning, EntryField, EntryIpAddr, PageInfo, Query, SearchEntry, SearchResult,
+    SearchType,
+};
+use crate::batch::{batch_with, BatchOptions, BatchOutcome};
+use crate::budget::{Budget, BudgetLimit, BudgetState};
+use crate::cassette::{Cassette, Interaction, RecordedRequest, RecordedResponse};
+#[cfg(feature = "config")]
+use crate::config::Config;
+use crate::credentials::{normalise_email, write_combolist, ExtractOptions, Identity, Secret};
+#[cfg(feature = "csv")]
+use crate::csv_export::{CsvColumn, CsvWriter};
+#[cfg(feature = "encryption")]
+use crate::encryption::ArchiveKey;
+use crate::graph::IdentityGraph;
+use crate::hash_export::{HashExport, HashFormat};
+use crate::hashes::{HashCandidate, HashKind};
+use crate::jsonl::{JsonlReader, JsonlWriter};
+#[cfg(feature = "mock")]
+use crate::mock::{MockFixture, MockServer, MOCK_EMAIL};
+use crate::multi::MultiSearchEntry;
+use crate::pivot::{pivot_with, PivotOptions};
+#[cfg(feature = "redact")]
+use crate::redact::{Redaction, RedactionPolicy};
+use crate::res::Entry;
+use crate::secret::{SecretString, REDACTED};
+#[cfg(feature = "verify")]
+use crate::verify::{verify_hash, Verification};
 #[cfg(feature = "tokio")]
 use crate::ScheduledRequest;
+use crate::{DehashedApi, DehashedError};
 
 fn setup() -> (DehashedApi, String) {
     let email = env::var("EMAIL").unwrap();
@@ -18,6 +49,10 @@ fn setup() -> (DehashedApi, String) {
     (api, search)
 }
 
+fn result(entries: serde_json::Value) -> SearchResult {
+    serde_json::from_value(serde_json::json!({ "entries": entries, "balance": 100 })).unwrap()
+}
+
 #[cfg(feature = "tokio")]
 #[tokio::test]
 async fn test_scheduler() {
@@ -48,3 +83,1318 @@ async fn test_normal() {
         .await
         .unwrap();
 }
+
+#[test]
+fn test_budget() {
+    let mut state = BudgetState::default();
+    state.set_budget(Budget {
+        min_balance: Some(10),
+        max_requests: Some(2),
+        ..Default::default()
+    });
+
+    state.reserve(1).unwrap();
+    state.spend();
+    state.update_balance(12);
+    state.reserve(1).unwrap();
+    state.spend();
+    state.update_balance(10);
+    assert!(matches!(
+        state.reserve(1),
+        Err(DehashedError::BudgetExceeded(BudgetLimit::MinBalance))
+    ));
+
+    state.update_balance(100);
+    assert!(matches!(
+        state.reserve(1),
+        Err(DehashedError::BudgetExceeded(BudgetLimit::Requests))
+    ));
+
+    // Reserved credits count against the limits until they are spent or released
+    let mut state = BudgetState::default();
+    state.set_budget(Budget {
+        max_credits: Some(5),
+        ..Default::default()
+    });
+    state.reserve(3).unwrap();
+    assert!(matches!(
+        state.reserve(3),
+        Err(DehashedError::BudgetExceeded(BudgetLimit::Credits))
+    ));
+    state.spend();
+    state.release(2);
+    state.reserve(3).unwrap();
+    assert!(state.reserve(2).is_err());
+}
+
+#[test]
+fn test_base_url() {
+    let mut api = DehashedApi::new("a@example.com".to_string(), "key".to_string()).unwrap();
+    assert!(matches!(
+        api.set_base_url("http://example.com/search".to_string()),
+        Err(DehashedError::InsecureBaseUrl(_))
+    ));
+    for url in [
+        "https://example.com/search",
+        "http://127.0.0.1:8080/search",
+        "http://localhost:8080/search",
+        "http://[::1]:8080/search",
+    ] {
+        api.set_base_url(url.to_string()).unwrap();
+    }
+    api.set_proxy(Some("http://127.0.0.1:3128")).unwrap();
+}
+
+#[tokio::test]
+async fn test_balance() {
+    let (api, _) = setup();
+
+    assert!(api.verify_credentials().await.unwrap());
+    let balance = api.balance().await.unwrap();
+    assert_eq!(api.remaining_balance(), Some(balance));
+}
+
+#[test]
+fn test_entry_fields() {
+    let entry: Entry = serde_json::from_str(
+        r#"{
+            "id": "42",
+            "email": "test@example.com",
+            "username": "",
+            "password": "hunter2",
+            "hashed_password": "",
+            "ip_address": "127.0.0.1",
+            "name": "",
+            "vin": "",
+            "address": "",
+            "phone": "",
+            "database_name": "Example",
+            "url": "https://example.com",
+            "dob": "",
+            "favourite_color": "blue"
+        }"#,
+    )
+    .unwrap();
+
+    let entry = SearchEntry::try_from(entry).unwrap();
+    assert_eq!(entry.id, 42);
+    assert_eq!(entry.username, None);
+    assert_eq!(entry.url.as_deref(), Some("https://example.com"));
+    assert_eq!(entry.dob, None);
+    assert_eq!(entry.extra["favourite_color"], "blue");
+}
+
+#[test]
+fn test_multi_entry() {
+    let entry: MultiSearchEntry = serde_json::from_str(
+        r#"{
+            "id": "7",
+            "email": ["a@example.com", "b@example.com"],
+            "username": "alice",
+            "password": null,
+            "phone": ""
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(entry.email, ["a@example.com", "b@example.com"]);
+    assert_eq!(entry.username, ["alice"]);
+    assert!(entry.password.is_empty());
+    assert!(entry.phone.is_empty());
+
+    let rows = entry.rows();
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|x| x.id == 7));
+    assert_eq!(rows[2].field, EntryField::Username);
+}
+
+#[test]
+fn test_multi_entry_comma_joined() {
+    let entry: MultiSearchEntry = serde_json::from_str(
+        r#"{
+            "id": 8,
+            "email": "a@example.com, b@example.com",
+            "ip_address": "10.0.0.1,,10.0.0.2",
+            "phone": ["1,2"],
+            "address": "Main Street 1, Springfield"
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(entry.email, ["a@example.com", "b@example.com"]);
+    assert_eq!(entry.ip_address, ["10.0.0.1", "10.0.0.2"]);
+    assert_eq!(entry.phone, ["1,2"]);
+    assert_eq!(entry.address, ["Main Street 1, Springfield"]);
+}
+
+#[test]
+fn test_lenient_conversion() {
+    let mut entry: Entry = serde_json::from_str(
+        r#"{
+            "id": "1",
+            "email": "",
+            "username": "",
+            "password": "",
+            "hashed_password": "",
+            "ip_address": "1.2.3.4, 5.6.7.8",
+            "name": "",
+            "vin": "",
+            "address": "",
+            "phone": "",
+            "database_name": ""
+        }"#,
+    )
+    .unwrap();
+    assert!(SearchEntry::try_from(entry.clone()).is_err());
+
+    let mut warnings = vec![];
+    let converted = SearchEntry::convert_lenient(entry.clone(), &mut warnings).unwrap();
+    assert_eq!(
+        converted.ip_address,
+        Some(EntryIpAddr::Raw("1.2.3.4, 5.6.7.8".to_string()))
+    );
+    assert_eq!(warnings[0].field, Some(EntryField::IpAddress));
+
+    entry.id = "invalid".to_string();
+    assert!(SearchEntry::convert_lenient(entry, &mut warnings).is_none());
+    assert!(matches!(
+        &warnings[1],
+        ConversionWarning { field: None, .. }
+    ));
+}
+
+#[test]
+fn test_merge() {
+    let mut old = result(serde_json::json!([
+        { "id": 1, "email": "a@example.com", "password": "123456" },
+        { "id": 2, "email": "b@example.com", "password": "123456" },
+    ]));
+    let mut new = result(serde_json::json!([
+        { "id": 2, "email": "b@example.com", "password": "123456" },
+        { "id": 3, "email": "a@example.com", "password": "123456" },
+    ]));
+    new.balance = 120;
+
+    let diff = SearchResult::diff(&old, &new);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].id, 3);
+    assert_eq!(diff.removed[0].id, 1);
+
+    old.merge(new);
+    assert_eq!(old.entries.len(), 4);
+    assert_eq!(old.balance, 120);
+    old.dedup_by_id();
+    assert_eq!(old.entries.len(), 3);
+    old.dedup_by_content();
+    assert_eq!(old.entries.len(), 2);
+    old.dedup_by_key(|x| x.password.clone());
+    assert_eq!(old.entries.len(), 1);
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv() {
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "a@example.com", "password": "pa\"ss,word" },
+        { "id": 2, "username": "bob", "password": "=1+1" },
+    ]));
+
+    let mut writer = CsvWriter::with_columns(
+        vec![],
+        vec![
+            CsvColumn::Id,
+            CsvColumn::Field(EntryField::Email),
+            CsvColumn::Field(EntryField::Password),
+        ],
+    );
+    writer.set_formula_escaping(true);
+    writer.write_result(&res).unwrap();
+    let csv = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+    assert_eq!(
+        csv,
+        "id,email,password\n1,a@example.com,\"pa\"\"ss,word\"\n2,,'=1+1\n"
+    );
+}
+
+#[test]
+fn test_jsonl() {
+    let mut res = result(serde_json::json!([
+        { "id": 1, "email": "a@example.com", "ip_address": "127.0.0.1" },
+        { "id": 2, "username": "bob", "extra_field": [1, 2] },
+    ]));
+    res.total = 5;
+
+    let archive = JsonlWriter::write_archive(vec![], "username:bob".to_string(), &res).unwrap();
+    assert_eq!(archive.iter().filter(|x| **x == b'\n').count(), 3);
+
+    let reader = JsonlReader::new(archive.as_slice()).unwrap();
+    assert_eq!(reader.header().query, "username:bob");
+    let read = reader.into_search_result().unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&read).unwrap(),
+        serde_json::to_value(&res).unwrap()
+    );
+
+    for archive in ["", "\n\n", "garbage\n{\"id\": 1}\n", "{\"id\": 1}\n"] {
+        assert!(
+            matches!(
+                JsonlReader::new(archive.as_bytes()),
+                Err(DehashedError::InvalidArchive)
+            ),
+            "{archive}"
+        );
+    }
+}
+
+#[test]
+fn test_hash_export() {
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "a@example.com", "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99" },
+        { "id": 2, "email": "b@example.com", "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99" },
+        { "id": 3, "username": "c", "hashed_password": "$2y$10$abcdefghijklmnopqrstuu5Lm9Zq4hZ0nC1k3Q0X9y8qYk7mP1p2a" },
+        { "id": 4, "username": "d", "hashed_password": "not a hash" },
+    ]));
+
+    let export = HashExport::from_result(&res);
+    assert_eq!(export.unknown, [4]);
+    assert_eq!(export.groups[&HashKind::Md5].len(), 1);
+    assert_eq!(export.groups[&HashKind::Md5][0].entry_ids, [1, 2]);
+    for (kind, mode) in [
+        (HashKind::Bcrypt, 3200),
+        (HashKind::Scrypt, 8900),
+        (HashKind::Pbkdf2Sha256, 20300),
+    ] {
+        assert_eq!(kind.hashcat_mode(), Some(mode), "{kind:?}");
+    }
+
+    let mut out = vec![];
+    export
+        .write(HashKind::Md5, HashFormat::HashcatWithUser, &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "a@example.com:5f4dcc3b5aa765d61d8327deb882cf99\n\
+         b@example.com:5f4dcc3b5aa765d61d8327deb882cf99\n"
+    );
+
+    // hashcat only parses the SCRYPT: format of scrypt
+    let res = result(serde_json::json!([
+        { "id": 1, "hashed_password": "SCRYPT:16384:8:1:c2FsdA==:aGFzaA==" },
+        { "id": 2, "hashed_password": "$7$C6..../....SodiumChloride$kBGj9fHznVYFQMEn/qDCfrDevf9YDtcDdKvEqHJLV8D" },
+        { "id": 3, "hashed_password": "$scrypt$ln=16,r=8,p=1$c2FsdA$aGFzaA" },
+    ]));
+    let export = HashExport::from_result(&res);
+    assert_eq!(export.groups[&HashKind::Scrypt].len(), 3);
+    let mut out = vec![];
+    export
+        .write(HashKind::Scrypt, HashFormat::Hashcat, &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "SCRYPT:16384:8:1:c2FsdA==:aGFzaA==\n"
+    );
+    let mut out = vec![];
+    export
+        .write(HashKind::Scrypt, HashFormat::John, &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 3);
+}
+
+#[test]
+fn test_hash_identification() {
+    let candidates = HashKind::identify("5F4DCC3B5AA765D61D8327DEB882CF99");
+    assert_eq!(candidates[0].kind, HashKind::Ntlm);
+    assert_eq!(candidates[1].kind, HashKind::Md5);
+    assert_eq!(candidates.len(), 3);
+    assert_eq!(
+        HashKind::detect("5F4DCC3B5AA765D61D8327DEB882CF99"),
+        Some(HashKind::Ntlm)
+    );
+    assert!(candidates
+        .windows(2)
+        .all(|x| x[0].confidence >= x[1].confidence));
+
+    assert_eq!(
+        HashKind::detect("5f4dcc3b5aa765d61d8327deb882cf99"),
+        Some(HashKind::Md5)
+    );
+    assert_eq!(
+        HashKind::identify("$argon2id$v=19$m=65536,t=3,p=4$c2FsdA$aGFzaA"),
+        [HashCandidate {
+            kind: HashKind::Argon2,
+            confidence: 1.0
+        }]
+    );
+    assert_eq!(
+        HashKind::detect("pbkdf2_sha256$260000$salt$hash="),
+        Some(HashKind::DjangoPbkdf2Sha256)
+    );
+    assert_eq!(
+        HashKind::detect("*2470C0C06DEE42FD1618BB99005ADCA2EC9D1E19"),
+        Some(HashKind::MySql41)
+    );
+    assert_eq!(HashKind::detect("hunter2"), None);
+    assert!(HashKind::Ntlm.is_fast());
+    assert!(!HashKind::Bcrypt.is_fast());
+}
+
+#[cfg(feature = "verify")]
+#[test]
+fn test_verify() {
+    // Known answers for every supported kind of "password"
+    for hash in [
+        "5f4dcc3b5aa765d61d8327deb882cf99",
+        "8a9d093f14f8701df17732b2bb182c74",
+        "8846F7EAEE8FB117AD06BDD830B7586C",
+        "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8",
+        "d63dc919e201d7bc4c825630d2cf25fdc93d4b2f0d46706d29038d01",
+        "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8",
+        "a8b64babd0aca91a59bdbb7761b421d4f2bb38280d3a75ba0f21f2bebc45583d446c598660c94ce680c47d19c30783a7",
+        "b109f3bbbc244eb82441917ed06d618b9008dd09b3befd1b5e07394c706a8bb980b1d7785e5976ec049b46df5f1326af5a2ea6d103fd07c95385ffab0cacbc86",
+        "*2470C0C06DEE42FD1618BB99005ADCA2EC9D1E19",
+        "sha1$salt$59b3e8d637cf97edbe2384cf59cb7453dfe30789",
+        "$2b$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+        "$2y$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm",
+        "$1$saltstri$qQY4WxjABChYG1ccLpfkz/",
+        "$5$saltstring$OH4IDuTlsuTYPdED1gsuiRMyTAwNlRWyA6Xr3I4/dQ5",
+        "$5$rounds=1000$saltstring$S2dlplsLzofRuJ/frAjtaYev58CdqEN6fUby2laILG1",
+        "$6$saltstri$k8VzDG9.mgjhbsEnRIs98eixmdsgruSnv9kcJXlkqui25vERmQ72wTScMbQlz9OGadWpihI.57kI/1k8pAAYh0",
+        "$6$rounds=1000$saltstring$EzTqOEb9gQc3Va/4p4pnqWqp/wIh1Otyhg9H9E8sV0eDyHGbNBkoMxYIM0ODHPBfmDNWb6wRiAvTriYxeDgb9.",
+    ] {
+        assert_eq!(verify_hash(hash, "password"), Verification::Match, "{hash}");
+        assert_eq!(
+            verify_hash(hash, "Password"),
+            Verification::NoMatch,
+            "{hash}"
+        );
+    }
+    // Passwords of different lengths exercise all branches of the crypt algorithms
+    for (hash, password) in [
+        ("$1$salt$UsdFqFVB.FsuinRDK5eE..", String::new()),
+        ("$1$salt$ZHAUuXk5QxccCMx6iuav31", "a".repeat(20)),
+        (
+            "$5$saltstring$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5",
+            "Hello world!".to_string(),
+        ),
+        (
+            "$5$rounds=1000$saltstring$DOVE14rKmQ/DIMPRXfrq1oiUzBI6tv9qhQhAY/ed4s9",
+            "b".repeat(40),
+        ),
+        (
+            "$6$rounds=1000$0123456789abcdef$fcX0kPBT3MB1yconyG4FGXg2BJk3EvxaE5/yciim5jGDq3lYeXoZ3aKVfWW1pqsPSPhOGHe7U/FSJrxTh7wNO0",
+            "b".repeat(70),
+        ),
+    ] {
+        assert_eq!(verify_hash(hash, &password), Verification::Match, "{hash}");
+    }
+    assert_eq!(
+        verify_hash("$argon2id$v=19$m=65536,t=3,p=4$c2FsdA$aGFzaA", "password"),
+        Verification::Unsupported
+    );
+    // The rounds of leaked hashes can't be trusted, checking them must stay cheap
+    for hash in [
+        "$5$rounds=100001$saltstring$S2dlplsLzofRuJ/frAjtaYev58CdqEN6fUby2laILG1",
+        "$6$rounds=999999999$saltstring$EzTqOEb9gQc3Va/4p4pnqWqp/wIh1Otyhg9H9E8sV0eDyHGbNBkoMxYIM0ODHPBfmDNWb6wRiAvTriYxeDgb9.",
+    ] {
+        assert_eq!(verify_hash(hash, "password"), Verification::Unsupported);
+    }
+
+    let res = result(serde_json::json!([
+        { "id": 1, "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99" },
+        { "id": 2, "email": "a@example.com" },
+    ]));
+    assert_eq!(
+        res.entries[0].verify_password("password"),
+        Verification::Match
+    );
+    assert_eq!(
+        res.entries[1].verify_password("password"),
+        Verification::Unsupported
+    );
+}
+
+#[test]
+fn test_password_report() {
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "a@example.com", "password": "Summer2023!", "database_name": "A" },
+        { "id": 2, "email": "B@example.com", "password": "Summer2023!", "database_name": "A" },
+        { "id": 3, "username": "c", "password": "123456", "database_name": "B" },
+        { "id": 4, "email": "d@example.com", "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99" },
+        { "id": 5, "email": "e@example.com" },
+    ]));
+
+    let report = PasswordReport::from_result(&res);
+    assert_eq!(report.passwords, 3);
+    assert_eq!(report.unique_passwords, 2);
+    assert_eq!(report.length_distribution[&11], 2);
+    assert_eq!(report.character_classes.all_classes, 2);
+    assert_eq!(report.character_classes.only_digits, 1);
+    assert_eq!(report.top_passwords[0].value, "Summer2023!");
+    assert_eq!(report.top_base_words[0].value, "summer");
+    assert_eq!(report.reuse.len(), 1);
+    assert_eq!(report.reuse[0].accounts, ["a@example.com", "b@example.com"]);
+    assert_eq!(report.exposure.plaintext, 3);
+    assert_eq!(report.exposure.hash_only, 1);
+    assert_eq!(report.exposure.none, 1);
+    assert_eq!(report.top_databases[0].count, 2);
+}
+
+#[test]
+fn test_credentials() {
+    assert_eq!(
+        normalise_email(" J.Doe+news@GoogleMail.com", true),
+        "jdoe@gmail.com"
+    );
+    assert_eq!(
+        normalise_email("J.Doe+news@example.com", true),
+        "j.doe+news@example.com"
+    );
+
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "J.Doe@gmail.com", "password": "hunter2" },
+        { "id": 2, "email": "jdoe@gmail.com", "password": "hunter2" },
+        { "id": 3, "email": "NULL", "username": "bob", "password": "NULL", "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99" },
+        { "id": 4, "username": "", "password": "secret" },
+    ]));
+
+    let credentials = res.credentials(&ExtractOptions {
+        fold_gmail: true,
+        include_hashes: true,
+    });
+    assert_eq!(credentials.len(), 2);
+    assert_eq!(credentials[0].entry_id, 1);
+    assert_eq!(
+        credentials[1].identity,
+        Identity::Username("bob".to_string())
+    );
+    assert!(matches!(
+        credentials[1].secret,
+        Secret::Hash {
+            kind: Some(HashKind::Md5),
+            ..
+        }
+    ));
+
+    let mut out = vec![];
+    write_combolist(&credentials, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "jdoe@gmail.com:hunter2\nbob:5f4dcc3b5aa765d61d8327deb882cf99\n"
+    );
+}
+
+#[test]
+fn test_identity_graph() {
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "A@example.com", "username": "alice" },
+        { "id": 2, "username": "Alice", "phone": "123, 456" },
+        { "id": 3, "email": "bob@example.com", "ip_address": "127.0.0.1" },
+        { "id": 4, "email": "a@example.com", "username": "alice" },
+    ]));
+
+    let graph = IdentityGraph::from_result(&res);
+    assert_eq!(graph.nodes.len(), 6);
+    assert_eq!(graph.edges.len(), 5);
+
+    let components = graph.components();
+    assert_eq!(components.len(), 2);
+    assert_eq!(components[0].len(), 4);
+
+    let related = graph.related(EntryField::Phone, "456");
+    assert_eq!(related.len(), 3);
+    assert!(related.iter().any(|x| x.value == "a@example.com"));
+
+    let mut dot = vec![];
+    graph.write_dot(&mut dot).unwrap();
+    assert!(String::from_utf8(dot)
+        .unwrap()
+        .contains("n0 -- n1 [weight=2];"));
+
+    let mut graphml = vec![];
+    graph.write_graphml(&mut graphml).unwrap();
+    assert!(String::from_utf8(graphml).unwrap().contains(
+        r#"<edge id="e0" source="n0" target="n1"><data key="entries">1,4</data></edge>"#
+    ));
+}
+
+#[tokio::test]
+async fn test_pivot() {
+    let search = |query: Query| async move {
+        let mut res = match query.to_string().as_str() {
+            "email:\"a@example.com\"" => result(serde_json::json!([
+                { "id": 1, "email": "a@example.com", "username": "alice" },
+            ])),
+            "username:\"alice\"" => result(serde_json::json!([
+                { "id": 1, "email": "a@example.com", "username": "alice" },
+                { "id": 2, "username": "alice", "phone": "123" },
+            ])),
+            "phone:\"123\"" => result(serde_json::json!([
+                { "id": 3, "phone": "123", "email": "c@example.com" },
+            ])),
+            _ => return Err(DehashedError::InvalidQuery),
+        };
+        res.pages = vec![PageInfo {
+            page: 1,
+            entries: res.entries.len(),
+            took: "1ms".to_string(),
+            requested_at: SystemTime::now(),
+        }];
+        Ok(res)
+    };
+
+    let pivot = pivot_with(
+        Query::Email(SearchType::Exact("a@example.com".to_string())),
+        &PivotOptions::default(),
+        1,
+        Duration::ZERO,
+        search,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(pivot.steps.len(), 3);
+    assert_eq!(pivot.credits, 3);
+    assert_eq!(pivot.result.entries.len(), 3);
+    let origin = pivot.steps[2].origin.as_ref().unwrap();
+    assert_eq!(origin.step, 1);
+    assert_eq!(origin.entry_id, 2);
+    assert_eq!(origin.field, EntryField::Phone);
+    assert!(!pivot.truncated);
+
+    let pivot = pivot_with(
+        Query::Email(SearchType::Exact("a@example.com".to_string())),
+        &PivotOptions {
+            max_credits: 1,
+            ..Default::default()
+        },
+        1,
+        Duration::ZERO,
+        search,
+    )
+    .await
+    .unwrap();
+    assert_eq!(pivot.steps.len(), 1);
+    assert!(pivot.truncated);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_pivot() {
+    let server = MockServer::start().unwrap();
+    let api = server.api().unwrap();
+    server.set_fixture(
+        r#"email:"a@example.com""#,
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 1, "email": "a@example.com", "username": "alice" }),
+        ])
+        .with_total(25_000),
+    );
+    server.set_fixture(
+        r#"username:"alice""#,
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 2, "username": "alice", "phone": "123" }),
+        ])
+        .with_total(25_000),
+    );
+    let seed = Query::Email(SearchType::Exact("a@example.com".to_string()));
+
+    // Every query may fetch 3 pages, so the third query could exceed the limit
+    let options = PivotOptions {
+        max_credits: 7,
+        ..Default::default()
+    };
+    let pivot = api.pivot(seed.clone(), &options).await.unwrap();
+    assert_eq!(pivot.steps.len(), 2);
+    assert_eq!(pivot.credits, 6);
+    assert_eq!(server.requests().len(), 6);
+    assert!(pivot.truncated);
+
+    let options = PivotOptions {
+        max_credits: 2,
+        ..Default::default()
+    };
+    let pivot = api.pivot(seed, &options).await.unwrap();
+    assert!(pivot.steps.is_empty());
+    assert_eq!(pivot.credits, 0);
+    assert_eq!(server.requests().len(), 6);
+}
+
+#[tokio::test]
+async fn test_batch() {
+    let queries = std::cell::RefCell::new(vec![]);
+    let search = |query: Query| {
+        queries.borrow_mut().push(query.to_string());
+        async move {
+            let q = query.to_string();
+            if q.contains("fail@example.com") {
+                return Err(DehashedError::Unknown);
+            }
+            let mut entries = vec![];
+            if q.contains("a@example.com") {
+                entries.push(serde_json::json!({ "id": 1, "email": "A@example.com" }));
+                entries
+                    .push(serde_json::json!({ "id": 2, "email": "a@example.com, c@example.com" }));
+            }
+            Ok(result(serde_json::Value::Array(entries)))
+        }
+    };
+
+    let res = batch_with(
+        [
+            "a@example.com",
+            "b@example.com",
+            "A@example.com",
+            "c@example.com",
+            "fail@example.com",
+        ],
+        EntryField::Email,
+        &BatchOptions {
+            max_query_length: 70,
+        },
+        Duration::ZERO,
+        search,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(res.items.len(), 4);
+    assert_eq!(res.queries, 2);
+    assert_eq!(
+        queries.borrow()[0],
+        r#"email:("a@example.com" OR "b@example.com" OR "c@example.com")"#
+    );
+    assert!(matches!(&res.items[0].outcome, BatchOutcome::Hits(x) if x.len() == 2));
+    assert!(matches!(res.items[1].outcome, BatchOutcome::NoHits));
+    assert!(matches!(&res.items[2].outcome, BatchOutcome::Hits(x) if x[0].id == 2));
+    assert!(matches!(res.items[3].outcome, BatchOutcome::Failed(_)));
+
+    assert!(matches!(
+        batch_with(
+            ["x"],
+            EntryField::Dob,
+            &BatchOptions::default(),
+            Duration::ZERO,
+            search
+        )
+        .await,
+        Err(DehashedError::InvalidQuery)
+    ));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config() {
+    let config = Config::parse(
+        r#"
+default_profile = "work"
+
+[profiles.work]
+email = "alice@example.com"
+api_key = "KEY"
+base_url = "https://dehashed.example.com/search"
+request_delay_ms = 500
+max_credits = 10
+
+[profiles.private]
+email = "bob@example.com"
+"#,
+    )
+    .unwrap();
+
+    let mut profile = config.profiles["work"].clone();
+    profile.apply_overrides(|name| (name == "DEHASHED_API_KEY").then(|| "other".to_string()));
+    assert_eq!(profile.email.as_deref(), Some("alice@example.com"));
+    assert_eq!(profile.api_key.as_ref().map(|x| x.expose()), Some("other"));
+    assert!(!format!("{profile:?}").contains("other"));
+
+    let api = profile.build().unwrap();
+    assert_eq!(api.request_delay(), std::time::Duration::from_millis(500));
+    assert_eq!(api.budget().max_credits, Some(10));
+
+    assert!(matches!(
+        config.profiles["private"].build(),
+        Err(DehashedError::MissingCredentials)
+    ));
+    assert!(matches!(
+        config.profile(Some("missing")),
+        Err(DehashedError::UnknownProfile(_))
+    ));
+    // Without a config file, every profile is provided by the environment
+    let config = Config {
+        missing_file: true,
+        ..Config::default()
+    };
+    assert!(config.profile(Some("missing")).is_ok());
+    assert!(matches!(
+        Config::parse("[profiles.work]\npassword = \"x\""),
+        Err(DehashedError::TomlError(_))
+    ));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = env::temp_dir().join(format!("dehashed-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "[profiles.work]\nemail = \"alice@example.com\"\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(matches!(
+            Config::load(&path),
+            Err(DehashedError::InsecureConfig(_))
+        ));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(Config::load(&path).unwrap().profiles.contains_key("work"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn test_secrets() {
+    let key = SecretString::new("secret-key".to_string());
+    assert_eq!(key.expose(), "secret-key");
+    assert_eq!(format!("{key:?}"), REDACTED);
+
+    let api = DehashedApi::new("alice@example.com".to_string(), "SECRET-KEY".to_string()).unwrap();
+    assert!(!format!("{api:?}").to_lowercase().contains("secret-key"));
+
+    let res = result(serde_json::json!([{
+        "id": 1,
+        "email": "alice@example.com",
+        "password": "hunter2",
+        "hashed_password": "5f4dcc3b5aa765d61d8327deb882cf99",
+        "database_name": "LeakDB",
+        "extra": { "api_token": "tok-123" },
+    }]));
+    let entry = &res.entries[0];
+    let debug = format!("{entry:?}");
+    assert!(debug.contains("alice@example.com") && debug.contains("api_token"));
+    assert!(!debug.contains("hunter2") && !debug.contains("5f4dcc3b"));
+    assert!(!debug.contains("tok-123"));
+    assert!(!format!("{res:?}").contains("hunter2"));
+    assert_eq!(
+        entry.to_string(),
+        "1: email=alice@example.com, password=<redacted>, hashed_password=<redacted>, \
+         database_name=LeakDB"
+    );
+
+    let multi = MultiSearchEntry::from(entry.clone());
+    let debug = format!("{multi:?}");
+    assert!(!debug.contains("hunter2") && !debug.contains("tok-123"));
+    let rows = format!("{:?}", multi.rows());
+    assert!(rows.contains("alice@example.com"));
+    assert!(!rows.contains("hunter2") && !rows.contains("5f4dcc3b"));
+
+    let credentials = format!("{:?}", res.credentials(&ExtractOptions::default()));
+    assert!(credentials.contains("alice@example.com"));
+    assert!(!credentials.contains("hunter2") && !credentials.contains("5f4dcc3b"));
+    let report = format!("{:?}", PasswordReport::from_result(&res));
+    assert!(!report.contains("hunter2"));
+    assert!(report.contains("LeakDB"));
+    assert!(!format!("{:?}", HashExport::from_result(&res)).contains("5f4dcc3b"));
+}
+
+#[cfg(feature = "redact")]
+#[test]
+fn test_redaction() {
+    let res = result(serde_json::json!([
+        {
+            "id": 1,
+            "email": "alice@example.com",
+            "password": "hunter2",
+            "ip_address": "10.0.0.1",
+            "database_name": "Leak",
+            "name": "Alice",
+        },
+        { "id": 2, "username": "Alice", "password": "hunter2", "extra": { "unknown": "x" } },
+    ]));
+
+    let policy = RedactionPolicy::shareable(b"0123456789abcdef0123456789abcdef");
+    let redacted = policy.redact_result(&res);
+    let (a, b) = (&redacted.entries[0], &redacted.entries[1]);
+    assert_eq!(a.email.as_deref(), Some("a***e@example.com"));
+    assert_eq!(b.username.as_deref(), Some("A***e"));
+    assert_eq!(a.database_name.as_deref(), Some("Leak"));
+    assert_eq!(a.name, None);
+    assert!(a.ip_address.is_none());
+    assert!(b.extra.is_empty());
+
+    let password = a.password.as_deref().unwrap();
+    assert_eq!(password.len(), 64);
+    assert_eq!(a.password, b.password);
+    assert_ne!(
+        password,
+        RedactionPolicy::shareable(b"other")
+            .redact_value(EntryField::Password, "hunter2")
+            .unwrap()
+    );
+
+    let policy = RedactionPolicy::new(b"key")
+        .with_field(EntryField::IpAddress, Redaction::Mask)
+        .with_field(EntryField::Name, Redaction::Drop);
+    let entry = policy.redact_entry(&res.entries[0]);
+    assert_eq!(
+        entry.ip_address,
+        Some(EntryIpAddr::Raw("1******1".to_string()))
+    );
+    assert_eq!(entry.name, None);
+    assert_eq!(entry.password.as_deref(), Some("hunter2"));
+    assert_eq!(policy.redact_entry(&res.entries[1]).extra.len(), 1);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encryption() {
+    let res = result(serde_json::json!([
+        { "id": 1, "email": "alice@example.com", "password": "hunter2" },
+        { "id": 2, "username": "bob", "ip_address": "10.0.0.1" },
+    ]));
+    let roundtrip = |key: &ArchiveKey, data: &[u8]| SearchResult::read_encrypted(data, key);
+
+    let key = ArchiveKey::generate();
+    let mut data = vec![];
+    res.write_encrypted(&mut data, &key).unwrap();
+    assert!(!String::from_utf8_lossy(&data).contains("hunter2"));
+    let read = roundtrip(&key, &data).unwrap();
+    assert_eq!(
+        serde_json::to_value(&read).unwrap(),
+        serde_json::to_value(&res).unwrap()
+    );
+
+    assert!(matches!(
+        roundtrip(&ArchiveKey::generate(), &data),
+        Err(DehashedError::DecryptionFailed)
+    ));
+    assert!(matches!(
+        roundtrip(&ArchiveKey::passphrase("x".to_string()), &data),
+        Err(DehashedError::DecryptionFailed)
+    ));
+    let mut tampered = data.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        roundtrip(&key, &tampered),
+        Err(DehashedError::DecryptionFailed)
+    ));
+    tampered = data.clone();
+    tampered[8] = 2;
+    assert!(matches!(
+        roundtrip(&key, &tampered),
+        Err(DehashedError::UnsupportedArchiveVersion(2))
+    ));
+    assert!(matches!(
+        roundtrip(&key, b"not an archive"),
+        Err(DehashedError::InvalidArchive)
+    ));
+
+    let key = ArchiveKey::passphrase("correct horse battery staple".to_string());
+    let mut data = vec![];
+    res.write_encrypted(&mut data, &key).unwrap();
+    assert_eq!(roundtrip(&key, &data).unwrap().entries.len(), 2);
+    assert!(matches!(
+        roundtrip(&ArchiveKey::passphrase("wrong".to_string()), &data),
+        Err(DehashedError::DecryptionFailed)
+    ));
+    // The memory, time and parallelism costs of the header are capped
+    for (offset, cost) in [(10, 1024 * 1024 + 1), (14, 65), (18, 65u32)] {
+        let mut tampered = data.clone();
+        tampered[offset..offset + 4].copy_from_slice(&cost.to_le_bytes());
+        assert!(
+            matches!(
+                roundtrip(&key, &tampered),
+                Err(DehashedError::InvalidArchive)
+            ),
+            "{offset}"
+        );
+    }
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_search() {
+    let server = MockServer::start().unwrap();
+    let api = server.api().unwrap();
+
+    let query = Query::Email(SearchType::Exact("alice@example.com".to_string()));
+    let entries = (0..10_005)
+        .map(|i| serde_json::json!({ "id": i.to_string(), "email": "alice@example.com" }))
+        .collect();
+    server.set_fixture(&query.to_string(), MockFixture::entries(entries));
+
+    let res = api.search(query.clone()).await.unwrap();
+    assert_eq!(res.entries.len(), 10_005);
+    assert_eq!(res.total, 10_005);
+    assert!(res.is_complete());
+    assert_eq!(res.pages.len(), 2);
+    assert_eq!(res.balance, 998);
+    assert_eq!(api.remaining_balance(), Some(998));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].query, r#"email:"alice@example.com""#);
+    assert_eq!((requests[1].page, requests[1].size), (2, 10_000));
+    assert!(requests.iter().all(|x| x.authorized));
+
+    let res = api
+        .search(Query::Username(SearchType::Simple("nobody".to_string())))
+        .await
+        .unwrap();
+    assert!(res.entries.is_empty());
+    assert_eq!(api.balance().await.unwrap(), 996);
+    assert_eq!(server.balance(), 996);
+
+    server.set_fixture(
+        "domain:big.example.com",
+        MockFixture::entries(vec![serde_json::json!({ "id": 1 })]).with_total(50_000),
+    );
+    let res = api
+        .search(Query::Domain(SearchType::Simple(
+            "big.example.com".to_string(),
+        )))
+        .await
+        .unwrap();
+    assert!(!res.is_complete());
+
+    // A null value is treated like a missing one instead of failing the whole page
+    server.set_fixture(
+        "domain:null.example.com",
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 1, "email": "a@example.com", "username": null, "ip_address": null }),
+            serde_json::json!({ "id": 2, "email": null, "username": "bob" }),
+        ]),
+    );
+    let res = api
+        .search_raw("domain:null.example.com".to_string())
+        .await
+        .unwrap();
+    assert_eq!(res.entries.len(), 2);
+    assert_eq!(res.entries[0].username, None);
+    assert_eq!(res.entries[0].ip_address, None);
+    assert_eq!(res.entries[1].email, None);
+    assert_eq!(res.entries[1].username.as_deref(), Some("bob"));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_search_arrays() {
+    let server = MockServer::start().unwrap();
+    let api = server.api().unwrap();
+
+    let query = Query::Domain(SearchType::Simple("x.com".to_string()));
+    server.set_fixture(
+        &query.to_string(),
+        MockFixture::entries(vec![
+            serde_json::json!({
+                "id": 1,
+                "email": ["a@x.com", "b@x.com"],
+                "username": ["alice"],
+                "ip_address": ["10.0.0.1", "10.0.0.2"],
+                "password": [],
+            }),
+            serde_json::json!({ "id": 2, "email": "c@x.com, d@x.com" }),
+        ]),
+    );
+
+    let res = api.search(query.clone()).await.unwrap();
+    assert_eq!(res.entries.len(), 2);
+    assert_eq!(res.entries[0].email.as_deref(), Some("a@x.com"));
+    assert_eq!(res.entries[0].username.as_deref(), Some("alice"));
+    assert_eq!(
+        res.entries[0].ip_address,
+        Some(EntryIpAddr::Parsed("10.0.0.1".parse().unwrap()))
+    );
+    assert_eq!(res.entries[0].password, None);
+
+    let res = api.search_multi(query).await.unwrap();
+    assert_eq!(res.entries.len(), 2);
+    assert_eq!(res.entries[0].email, ["a@x.com", "b@x.com"]);
+    assert_eq!(res.entries[0].ip_address, ["10.0.0.1", "10.0.0.2"]);
+    assert!(res.entries[0].password.is_empty());
+    assert_eq!(res.entries[1].email, ["c@x.com", "d@x.com"]);
+    assert_eq!(res.balance, server.balance());
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_budget() {
+    let server = MockServer::start().unwrap();
+    let api = server.api().unwrap();
+
+    let query = Query::Domain(SearchType::Simple("x.com".to_string()));
+    let entries = (0..25_000)
+        .map(|i| serde_json::json!({ "id": i }))
+        .collect();
+    server.set_fixture(&query.to_string(), MockFixture::entries(entries));
+
+    // A search that may need more credits than allowed fails before the first request
+    api.set_budget(Budget {
+        max_credits: Some(2),
+        ..Default::default()
+    });
+    assert!(matches!(
+        api.search(query.clone()).await,
+        Err(DehashedError::BudgetExceeded(BudgetLimit::Credits))
+    ));
+    assert!(server.requests().is_empty());
+
+    // The balance is fetched first, if the minimum balance can't be checked otherwise
+    server.set_balance(5);
+    api.set_budget(Budget {
+        min_balance: Some(100),
+        ..Default::default()
+    });
+    assert!(matches!(
+        api.search(query.clone()).await,
+        Err(DehashedError::BudgetExceeded(BudgetLimit::MinBalance))
+    ));
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(api.remaining_balance(), Some(4));
+
+    api.set_budget(Budget::default());
+    let res = api.search(query).await.unwrap();
+    assert_eq!(res.entries.len(), 25_000);
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_errors() {
+    let server = MockServer::start().unwrap();
+    let api = server.api().unwrap();
+    let search = |value: &str| api.search(Query::Domain(SearchType::Simple(value.to_string())));
+
+    for (status, value) in [
+        (302, "invalid"),
+        (400, "limited"),
+        (401, "denied"),
+        (500, "error"),
+    ] {
+        server.set_fixture(&format!("domain:{value}"), MockFixture::status(status));
+    }
+    assert!(matches!(
+        search("invalid").await,
+        Err(DehashedError::InvalidQuery)
+    ));
+    assert!(matches!(
+        search("limited").await,
+        Err(DehashedError::RateLimited)
+    ));
+    assert!(matches!(
+        search("denied").await,
+        Err(DehashedError::Unauthorized)
+    ));
+    assert!(matches!(search("error").await, Err(DehashedError::Unknown)));
+    assert_eq!(server.balance(), 1_000);
+
+    server.set_fixture(
+        "domain:slow",
+        MockFixture::default().with_delay(std::time::Duration::from_millis(200)),
+    );
+    let start = std::time::Instant::now();
+    search("slow").await.unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+
+    let mut other = DehashedApi::new(MOCK_EMAIL.to_string(), "wrong".to_string()).unwrap();
+    other.set_base_url(server.url()).unwrap();
+    assert!(!other.verify_credentials().await.unwrap());
+    assert!(!server.requests().last().unwrap().authorized);
+
+    api.set_budget(Budget {
+        min_balance: Some(server.balance()),
+        ..Budget::default()
+    });
+    assert!(matches!(
+        search("example.com").await,
+        Err(DehashedError::BudgetExceeded(BudgetLimit::MinBalance))
+    ));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_query_splitting() {
+    let server = MockServer::start().unwrap();
+    let mut api = server.api().unwrap();
+    let query = "domain:example.com";
+    server.set_fixture(
+        query,
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 1, "email": "a@example.com" }),
+        ])
+        .with_total(40_000),
+    );
+    server.set_fixture(
+        "(domain:example.com) AND email:a*",
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 1, "email": "a@example.com" }),
+            serde_json::json!({ "id": 2, "email": "ab@example.com" }),
+        ])
+        .with_total(40_000),
+    );
+    server.set_fixture(
+        "(domain:example.com) AND email:ab*",
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 3, "email": "abc@example.com" }),
+        ])
+        .with_total(40_000),
+    );
+
+    // Without splitting, the search stops at the end of the result window
+    let res = api.search_raw(query.to_string()).await.unwrap();
+    assert_eq!(res.entries.len(), 1);
+    assert_eq!(server.requests().len(), 3);
+
+    assert!(matches!(
+        api.set_query_splitting(Some(QuerySplitting {
+            field: EntryField::Dob,
+            max_depth: 1,
+        })),
+        Err(DehashedError::UnsearchableField(EntryField::Dob))
+    ));
+    api.set_query_splitting(Some(QuerySplitting {
+        field: EntryField::Email,
+        max_depth: 1,
+    }))
+    .unwrap();
+    let res = api.search_raw(query.to_string()).await.unwrap();
+    let ids: Vec<u64> = res.entries.iter().map(|x| x.id).collect();
+    assert_eq!(ids, [1, 2]);
+    assert_eq!(res.total, 40_000);
+    // 3 pages of the query and of the `a` prefix, a single page of the other 35 prefixes
+    assert_eq!(server.requests().len(), 3 + 3 + 3 + 35);
+
+    api.set_query_splitting(Some(QuerySplitting {
+        field: EntryField::Email,
+        max_depth: 2,
+    }))
+    .unwrap();
+    let res = api.search_raw(query.to_string()).await.unwrap();
+    let ids: Vec<u64> = res.entries.iter().map(|x| x.id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+    // The `ab` prefix still exceeds the window, but isn't extended any further
+    assert_eq!(server.requests().len(), 44 + 3 + 3 + 35 + 3 + 35);
+    assert!(server
+        .requests()
+        .iter()
+        .all(|x| !x.query.contains("email:aba*")));
+}
+
+#[tokio::test]
+async fn test_cassette_replay() {
+    let request = |query: &str| RecordedRequest {
+        query: query.to_string(),
+        page: 1,
+        size: 10_000,
+    };
+    let cassette = Cassette {
+        interactions: vec![
+            Interaction {
+                request: request("domain:example.com"),
+                response: RecordedResponse {
+                    status: 200,
+                    body: serde_json::json!({
+                        "balance": 42,
+                        "entries": [{
+                            "id": "1", "email": "alice@example.com", "username": "",
+                            "password": "hunter2", "hashed_password": "", "ip_address": "",
+                            "name": "", "vin": "", "address": "", "phone": "",
+                            "database_name": "Leak",
+                        }],
+                        "success": true,
+                        "took": "1ms",
+                        "total": 1,
+                    })
+                    .to_string(),
+                },
+            },
+            Interaction {
+                request: request("domain:denied.com"),
+                response: RecordedResponse {
+                    status: 401,
+                    body: String::new(),
+                },
+            },
+        ],
+    };
+    assert!(!format!("{cassette:?}").contains("hunter2"));
+
+    let mut api = DehashedApi::new("a@example.com".to_string(), "key".to_string()).unwrap();
+    // Replayed requests don't count against the budget
+    api.set_budget(Budget {
+        min_balance: Some(100),
+        max_requests: Some(1),
+        ..Budget::default()
+    });
+    api.replay(cassette);
+    let search = |value: &str| api.search(Query::Domain(SearchType::Simple(value.to_string())));
+
+    let res = search("example.com").await.unwrap();
+    assert_eq!(res.entries[0].password.as_deref(), Some("hunter2"));
+    assert_eq!(res.balance, 42);
+    assert_eq!(api.remaining_balance(), None);
+    assert!(matches!(
+        search("denied.com").await,
+        Err(DehashedError::Unauthorized)
+    ));
+    assert!(matches!(
+        search("example.com").await,
+        Err(DehashedError::UnmatchedRequest(x)) if x == request("domain:example.com")
+    ));
+    assert!(matches!(
+        search("other.com").await,
+        Err(DehashedError::UnmatchedRequest(_))
+    ));
+}
+
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_cassette_recording() {
+    let server = MockServer::start().unwrap();
+    server.set_fixture(
+        "domain:example.com",
+        MockFixture::entries(vec![serde_json::json!({ "id": 1, "password": "hunter2" })]),
+    );
+    server.set_fixture("domain:limited.com", MockFixture::status(400));
+
+    let path = env::temp_dir().join(format!("dehashed-cassette-{}.json", std::process::id()));
+    let mut api = server.api().unwrap();
+    api.start_recording_to(path.clone()).unwrap();
+    let search = |api: &DehashedApi, value: &str| {
+        let api = api.clone();
+        let query = Query::Domain(SearchType::Simple(value.to_string()));
+        async move { api.search(query).await }
+    };
+    let recorded = search(&api, "example.com").await.unwrap();
+    assert!(search(&api, "limited.com").await.is_err());
+
+    let cassette = api.stop_cassette().unwrap();
+    assert_eq!(cassette.interactions.len(), 2);
+    assert_eq!(cassette.interactions[1].response.status, 400);
+    // The recording was saved after every interaction
+    assert_eq!(Cassette::load(&path).unwrap(), cassette);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        cassette.save(&path).unwrap();
+        assert_eq!(mode(&path), 0o600);
+    }
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(!content.contains(crate::mock::MOCK_API_KEY));
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    drop(server);
+    let mut api = DehashedApi::new("a@example.com".to_string(), "key".to_string()).unwrap();
+    api.replay(cassette);
+    let replayed = search(&api, "example.com").await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&replayed.entries).unwrap(),
+        serde_json::to_value(&recorded.entries).unwrap()
+    );
+    assert!(matches!(
+        search(&api, "limited.com").await,
+        Err(DehashedError::RateLimited)
+    ));
+    assert!(api.cassette().is_some());
+}
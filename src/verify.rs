@@ -0,0 +1,118 @@
+use md4::Md4;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+use crate::api::SearchEntry;
+use crate::crypt::verify_crypt;
+use crate::hashes::HashKind;
+
+/// The outcome of checking a candidate password
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Verification {
+    /// The candidate matches
+    Match,
+    /// The candidate doesn't match
+    NoMatch,
+    /// There is no password or hash that could be checked
+    Unsupported,
+}
+
+/// Check a candidate password against a hash.
+///
+/// Every algorithm returned by [HashKind::identify] is tried,
+/// so ambiguous shapes like MD5 and NTLM are both checked.
+/// DES-crypt, SHA1-crypt and the slow key derivation functions like scrypt or argon2
+/// are not supported, neither are SHA-crypt hashes with more than 100,000 rounds,
+/// as checking them would take too long.
+/// The check is done locally, the candidate never leaves the process.
+pub fn verify_hash(hash: &str, candidate: &str) -> Verification {
+    let hash = hash.trim();
+    let mut verification = Verification::Unsupported;
+    for kind in HashKind::identify(hash).into_iter().map(|x| x.kind) {
+        match verify_kind(kind, hash, candidate) {
+            Some(true) => return Verification::Match,
+            Some(false) => verification = Verification::NoMatch,
+            None => {}
+        }
+    }
+    verification
+}
+
+/// Check a candidate against a hash of a specific algorithm, [None] if it isn't supported
+fn verify_kind(kind: HashKind, hash: &str, candidate: &str) -> Option<bool> {
+    let pw = candidate.as_bytes();
+    Some(match kind {
+        HashKind::Md5 => hash.eq_ignore_ascii_case(&hex(&Md5::digest(pw))),
+        HashKind::Md4 => hash.eq_ignore_ascii_case(&hex(&Md4::digest(pw))),
+        HashKind::Ntlm => {
+            let utf16: Vec<u8> = candidate
+                .encode_utf16()
+                .flat_map(|x| x.to_le_bytes())
+                .collect();
+            hash.eq_ignore_ascii_case(&hex(&Md4::digest(utf16)))
+        }
+        HashKind::Sha1 => hash.eq_ignore_ascii_case(&hex(&Sha1::digest(pw))),
+        HashKind::Sha224 => hash.eq_ignore_ascii_case(&hex(&Sha224::digest(pw))),
+        HashKind::Sha256 => hash.eq_ignore_ascii_case(&hex(&Sha256::digest(pw))),
+        HashKind::Sha384 => hash.eq_ignore_ascii_case(&hex(&Sha384::digest(pw))),
+        HashKind::Sha512 => hash.eq_ignore_ascii_case(&hex(&Sha512::digest(pw))),
+        HashKind::MySql41 => {
+            let hash = hash.trim_start_matches('*');
+            hash.eq_ignore_ascii_case(&hex(&Sha1::digest(Sha1::digest(pw))))
+        }
+        HashKind::DjangoSha1 => {
+            let mut parts = hash.splitn(3, '$').skip(1);
+            let (Some(salt), Some(hash)) = (parts.next(), parts.next()) else {
+                return Some(false);
+            };
+            hash.eq_ignore_ascii_case(&hex(&Sha1::digest(format!("{salt}{candidate}"))))
+        }
+        HashKind::Bcrypt => return bcrypt::verify(candidate, hash).ok(),
+        HashKind::Md5Crypt | HashKind::Sha256Crypt | HashKind::Sha512Crypt => {
+            return verify_crypt(hash, candidate)
+        }
+        HashKind::DesCrypt
+        | HashKind::Sha1Crypt
+        | HashKind::Yescrypt
+        | HashKind::Scrypt
+        | HashKind::Argon2
+        | HashKind::MySql323
+        | HashKind::Phpass
+        | HashKind::DjangoPbkdf2Sha256
+        | HashKind::Pbkdf2Sha256
+        | HashKind::Ssha => return None,
+    })
+}
+
+/// Encode bytes as lower case hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+impl SearchEntry {
+    /// Check whether a candidate matches the password of this entry.
+    ///
+    /// The plaintext [SearchEntry::password] is compared first,
+    /// afterwards the [SearchEntry::hashed_password] is checked using [verify_hash].
+    pub fn verify_password(&self, candidate: &str) -> Verification {
+        let mut verification = Verification::Unsupported;
+        if let Some(password) = &self.password {
+            if password == candidate {
+                return Verification::Match;
+            }
+            verification = Verification::NoMatch;
+        }
+        if let Some(hash) = &self.hashed_password {
+            match verify_hash(hash, candidate) {
+                Verification::Match => return Verification::Match,
+                Verification::NoMatch => verification = Verification::NoMatch,
+                Verification::Unsupported => {}
+            }
+        }
+        verification
+    }
+}
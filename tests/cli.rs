@@ -0,0 +1,151 @@
+//! Runs the `dehashed` binary against a [MockServer]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use dehashed_rs::{MockFixture, MockServer, MOCK_API_KEY, MOCK_EMAIL};
+
+/// Run the binary with the credentials of the mock server and return the exit code and stdout
+fn dehashed(server: &MockServer, api_key: &str, args: &[&str], stdin: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dehashed"))
+        .args(args)
+        .env("DEHASHED_CONFIG", "/nonexistent/dehashed/config.toml")
+        .env("DEHASHED_EMAIL", MOCK_EMAIL)
+        .env("DEHASHED_API_KEY", api_key)
+        .env("DEHASHED_BASE_URL", server.url())
+        .env_remove("DEHASHED_PROXY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+fn server() -> MockServer {
+    let server = MockServer::start().unwrap();
+    server.set_fixture(
+        "domain:example.com",
+        MockFixture::entries(vec![serde_json::json!({
+            "id": 1,
+            "email": "alice@example.com",
+            "password": "hunter2",
+        })]),
+    );
+    server.set_fixture(
+        r#"email:"alice@example.com""#,
+        MockFixture::entries(vec![
+            serde_json::json!({ "id": 1, "email": "alice@example.com" }),
+        ]),
+    );
+    server.set_fixture(r#"email:"fail@example.com""#, MockFixture::status(500));
+    server.set_fixture("domain:limited.com", MockFixture::status(400));
+    server.set_fixture("domain:error.com", MockFixture::status(500));
+    server
+}
+
+#[test]
+fn test_search_formats() {
+    let server = server();
+    let search = |format: &str| {
+        let args = [
+            "search",
+            "--query",
+            "domain:example.com",
+            "--format",
+            format,
+        ];
+        let (code, stdout) = dehashed(&server, MOCK_API_KEY, &args, "");
+        assert_eq!(code, 0, "{format}");
+        stdout
+    };
+
+    let table = search("table");
+    assert!(table.lines().next().unwrap().starts_with("id"));
+    assert!(table.contains("alice@example.com"));
+    assert!(table.contains("1 of 1 entries"));
+
+    let json: serde_json::Value = serde_json::from_str(&search("json")).unwrap();
+    assert_eq!(json["entries"][0]["email"], "alice@example.com");
+
+    let jsonl = search("jsonl");
+    assert!(jsonl.lines().count() >= 2);
+    for line in jsonl.lines() {
+        serde_json::from_str::<serde_json::Value>(line).unwrap();
+    }
+    assert!(jsonl.contains("alice@example.com"));
+
+    let csv = search("csv");
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().contains("email"));
+    assert!(lines.next().unwrap().contains("alice@example.com"));
+}
+
+#[test]
+fn test_search_exit_codes() {
+    let server = server();
+    let search = |api_key: &str, query: &str| {
+        dehashed(&server, api_key, &["search", "--query", query], "").0
+    };
+
+    assert_eq!(search(MOCK_API_KEY, "domain:example.com"), 0);
+    assert_eq!(search(MOCK_API_KEY, "domain:nothing.com"), 3);
+    assert_eq!(search("wrong", "domain:example.com"), 4);
+    assert_eq!(search(MOCK_API_KEY, "domain:limited.com"), 5);
+    assert_eq!(search(MOCK_API_KEY, "domain:error.com"), 1);
+}
+
+#[test]
+fn test_batch() {
+    let server = server();
+    let batch = |format: &str, values: &str| {
+        let args = ["batch", "--field", "email", "--format", format, "-"];
+        dehashed(&server, MOCK_API_KEY, &args, values)
+    };
+
+    let (code, stdout) = batch("table", "alice@example.com\n");
+    assert_eq!(code, 0);
+    assert!(stdout.contains("alice@example.com\thits\t1"));
+
+    let (code, stdout) = batch("csv", "alice@example.com\n");
+    assert_eq!(code, 0);
+    assert!(stdout.contains("alice@example.com,hits,1"));
+
+    let (code, stdout) = batch("json", "alice@example.com\n");
+    assert_eq!(code, 0);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["items"][0]["value"], "alice@example.com");
+
+    let (code, stdout) = batch("jsonl", "alice@example.com\n");
+    assert_eq!(code, 0);
+    let item: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(item["value"], "alice@example.com");
+
+    assert_eq!(batch("table", "bob@example.com\n").0, 3);
+    assert_eq!(batch("table", "fail@example.com\n").0, 1);
+}
+
+#[test]
+fn test_balance() {
+    let server = server();
+    let (code, stdout) = dehashed(&server, MOCK_API_KEY, &["balance"], "");
+    assert_eq!(code, 0);
+    assert_eq!(stdout.trim(), "999");
+
+    let (code, _) = dehashed(&server, "wrong", &["balance"], "");
+    assert_eq!(code, 4);
+
+    // Without a config file, a named profile is taken from the environment
+    let (code, _) = dehashed(&server, MOCK_API_KEY, &["--profile", "work", "balance"], "");
+    assert_eq!(code, 0);
+}